@@ -54,11 +54,10 @@ impl BezierCurve {
                             .add_sized([45.0, 10.0], egui::Button::new("-"))
                             .on_hover_text("Remove last line segment")
                             .clicked()
+                            && (self.points.len() - 2) >= 3
                         {
-                            if (self.points.len() - 2) >= 3 {
-                                self.points.pop();
-                                self.points.pop();
-                            }
+                            self.points.pop();
+                            self.points.pop();
                         }
 
                         if ui
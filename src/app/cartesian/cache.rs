@@ -0,0 +1,110 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use exmex::prelude::*;
+
+use super::derivative;
+use super::ops::{self, Expr};
+use super::symbols::SymbolError;
+
+/// An expression parsed once with every symbol it references already bound,
+/// so that only the free variables are supplied at evaluation time.
+#[derive(Clone, Debug)]
+pub struct CompiledExpression {
+    expr: Expr,
+    /// The bound values, with the free variables written into their slots
+    /// in place on every evaluation.
+    args: RefCell<Vec<f64>>,
+    free_slots: Vec<Option<usize>>,
+}
+
+impl CompiledExpression {
+    pub fn eval(&self, free: &[f64]) -> Option<f64> {
+        let mut args = self.args.borrow_mut();
+        for (slot, value) in self.free_slots.iter().zip(free) {
+            if let Some(slot) = slot {
+                args[*slot] = *value;
+            }
+        }
+        self.expr.eval(&args).ok()
+    }
+}
+
+struct Cached {
+    versions: Vec<u64>,
+    compiled: CompiledExpression,
+    used: bool,
+}
+
+/// Compiled expressions keyed on their source text and free variables. An
+/// entry is rebound only when the versions of the symbols it depends on
+/// change, and re-parsed only when its text does.
+#[derive(Default)]
+pub struct ExpressionCache {
+    entries: HashMap<(String, Vec<String>), Cached>,
+    derivatives: HashMap<(String, String, usize), (String, bool)>,
+}
+
+impl ExpressionCache {
+    pub fn compile(
+        &mut self,
+        source: &str,
+        free: &[String],
+        versions: Vec<u64>,
+        lookup: impl Fn(&str) -> Option<f64>,
+    ) -> Result<CompiledExpression, SymbolError> {
+        let key = (source.to_string(), free.to_vec());
+        let expr = match self.entries.get_mut(&key) {
+            Some(cached) if cached.versions == versions => {
+                cached.used = true;
+                return Ok(cached.compiled.clone());
+            }
+            Some(cached) => cached.compiled.expr.clone(),
+            None => ops::parse(source).map_err(|e| SymbolError::Syntax(e.to_string()))?,
+        };
+
+        let mut args = vec![0.0; expr.var_names().len()];
+        let mut free_slots = vec![None; free.len()];
+        for (slot, name) in expr.var_names().iter().enumerate() {
+            if let Some(i) = free.iter().position(|free_name| free_name == name) {
+                free_slots[i] = Some(slot);
+            } else {
+                args[slot] =
+                    lookup(name).ok_or_else(|| SymbolError::Undefined(vec![name.clone()]))?;
+            }
+        }
+
+        let compiled = CompiledExpression {
+            expr,
+            args: RefCell::new(args),
+            free_slots,
+        };
+        self.entries.insert(
+            key,
+            Cached {
+                versions,
+                compiled: compiled.clone(),
+                used: true,
+            },
+        );
+        Ok(compiled)
+    }
+
+    /// Source of the `order`-th derivative of `source` with respect to `var`,
+    /// differentiated once per distinct source.
+    pub fn derivative(&mut self, source: &str, var: &str, order: usize) -> String {
+        let (derived, used) = self
+            .derivatives
+            .entry((source.to_string(), var.to_string(), order))
+            .or_insert_with(|| (derivative::derivative(source, var, order), true));
+        *used = true;
+        derived.clone()
+    }
+
+    /// Drops every entry that was not compiled since the previous sweep.
+    pub fn sweep(&mut self) {
+        self.entries
+            .retain(|_, cached| std::mem::take(&mut cached.used));
+        self.derivatives.retain(|_, (_, used)| std::mem::take(used));
+    }
+}
@@ -0,0 +1,250 @@
+use std::f64::consts::{E, PI, TAU};
+
+use num_complex::Complex64;
+
+use super::symbols::tokenize;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Exp,
+    Ln,
+    Log2,
+    Log10,
+    Sqrt,
+    Cbrt,
+    Abs,
+    Re,
+    Im,
+    Conj,
+    Arg,
+}
+
+impl Function {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "sin" => Function::Sin,
+            "cos" => Function::Cos,
+            "tan" => Function::Tan,
+            "asin" => Function::Asin,
+            "acos" => Function::Acos,
+            "atan" => Function::Atan,
+            "sinh" => Function::Sinh,
+            "cosh" => Function::Cosh,
+            "tanh" => Function::Tanh,
+            "exp" => Function::Exp,
+            "ln" | "log" => Function::Ln,
+            "log2" => Function::Log2,
+            "log10" => Function::Log10,
+            "sqrt" => Function::Sqrt,
+            "cbrt" => Function::Cbrt,
+            "abs" => Function::Abs,
+            "re" => Function::Re,
+            "im" => Function::Im,
+            "conj" => Function::Conj,
+            "arg" => Function::Arg,
+            _ => return None,
+        })
+    }
+
+    fn apply(self, z: Complex64) -> Complex64 {
+        match self {
+            Function::Sin => z.sin(),
+            Function::Cos => z.cos(),
+            Function::Tan => z.tan(),
+            Function::Asin => z.asin(),
+            Function::Acos => z.acos(),
+            Function::Atan => z.atan(),
+            Function::Sinh => z.sinh(),
+            Function::Cosh => z.cosh(),
+            Function::Tanh => z.tanh(),
+            Function::Exp => z.exp(),
+            Function::Ln => z.ln(),
+            Function::Log2 => z.log2(),
+            Function::Log10 => z.log10(),
+            Function::Sqrt => z.sqrt(),
+            Function::Cbrt => z.cbrt(),
+            Function::Abs => z.norm().into(),
+            Function::Re => z.re.into(),
+            Function::Im => z.im.into(),
+            Function::Conj => z.conj(),
+            Function::Arg => z.arg().into(),
+        }
+    }
+}
+
+/// A function of a complex `z`, parsed from the source of an expression as
+/// it is handed to exmex, with the imaginary unit written `i`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Constant(Complex64),
+    Z,
+    Neg(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+    Pow(Box<Expr>, Box<Expr>),
+    Call(Function, Box<Expr>),
+}
+
+impl Expr {
+    pub fn eval(&self, z: Complex64) -> Complex64 {
+        match self {
+            Expr::Constant(c) => *c,
+            Expr::Z => z,
+            // Subtracting keeps the imaginary part of `-1` at +0, which puts
+            // `sqrt(-1)` on the right side of the branch cut.
+            Expr::Neg(a) => Complex64::default() - a.eval(z),
+            Expr::Add(a, b) => a.eval(z) + b.eval(z),
+            Expr::Sub(a, b) => a.eval(z) - b.eval(z),
+            Expr::Mul(a, b) => a.eval(z) * b.eval(z),
+            Expr::Div(a, b) => a.eval(z) / b.eval(z),
+            Expr::Pow(a, b) => {
+                let (a, b) = (a.eval(z), b.eval(z));
+                // Whole powers are exact, and defined at zero.
+                if b.im == 0.0 && b.re.fract() == 0.0 && b.re.abs() <= 64.0 {
+                    a.powi(b.re as i32)
+                } else {
+                    a.powc(b)
+                }
+            }
+            Expr::Call(function, a) => function.apply(a.eval(z)),
+        }
+    }
+}
+
+/// Parses `source`, in which a symbol `{name}` stands for the number
+/// `lookup` gives for it.
+pub fn parse(source: &str, lookup: impl Fn(&str) -> Option<f64>) -> Result<Expr, String> {
+    let tokens = tokenize(source)
+        .into_iter()
+        .map(|token| &source[token.span])
+        .collect();
+    let mut parser = Parser {
+        tokens,
+        next: 0,
+        lookup: &lookup,
+    };
+    let expr = parser.sum()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(format!("unexpected {}", token)),
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<&'a str>,
+    next: usize,
+    lookup: &'a dyn Fn(&str) -> Option<f64>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.next).copied()
+    }
+
+    fn take(&mut self) -> Option<&'a str> {
+        let token = self.peek();
+        self.next += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.take() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("expected {} but found {}", expected, token)),
+            None => Err(format!("expected {}", expected)),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut expr = self.product()?;
+        while let Some(op @ ("+" | "-")) = self.peek() {
+            self.next += 1;
+            let rhs = Box::new(self.product()?);
+            expr = match op {
+                "+" => Expr::Add(Box::new(expr), rhs),
+                _ => Expr::Sub(Box::new(expr), rhs),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while let Some(op @ ("*" | "/")) = self.peek() {
+            self.next += 1;
+            let rhs = Box::new(self.unary()?);
+            expr = match op {
+                "*" => Expr::Mul(Box::new(expr), rhs),
+                _ => Expr::Div(Box::new(expr), rhs),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some("-") => {
+                self.next += 1;
+                Ok(Expr::Neg(Box::new(self.unary()?)))
+            }
+            Some("+") => {
+                self.next += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.atom()?;
+        if self.peek() == Some("^") {
+            self.next += 1;
+            return Ok(Expr::Pow(Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        let token = self.take().ok_or("unexpected end")?;
+        let constant = |re: f64| Ok(Expr::Constant(re.into()));
+        match token {
+            "(" => {
+                let expr = self.sum()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            "{" => {
+                let name = self.take().ok_or("unexpected end")?;
+                self.expect("}")?;
+                constant((self.lookup)(name).ok_or_else(|| format!("{} is undefined", name))?)
+            }
+            "z" => Ok(Expr::Z),
+            "i" => Ok(Expr::Constant(Complex64::i())),
+            "π" | "PI" => constant(PI),
+            "τ" | "TAU" => constant(TAU),
+            "e" | "E" => constant(E),
+            _ => {
+                if let Ok(number) = token.parse() {
+                    return constant(number);
+                }
+                let function = Function::from_name(token)
+                    .ok_or_else(|| format!("{} is not defined for complex numbers", token))?;
+                self.expect("(")?;
+                let arg = self.sum()?;
+                self.expect(")")?;
+                Ok(Expr::Call(function, Box::new(arg)))
+            }
+        }
+    }
+}
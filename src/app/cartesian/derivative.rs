@@ -0,0 +1,70 @@
+use exmex::prelude::*;
+
+use super::ops;
+use super::symbols::{substitute, tokenize, TokenKind};
+
+/// Operators exmex has no derivative for.
+const NOT_DIFFERENTIABLE: &[&str] = &[
+    "abs",
+    "signum",
+    "floor",
+    "round",
+    "ceil",
+    "trunc",
+    "fract",
+    "cbrt",
+    "atan2",
+    "min",
+    "max",
+    "lt",
+    "le",
+    "gt",
+    "ge",
+    "when",
+    "otherwise",
+];
+
+/// Source of the `order`-th derivative of `source` with respect to `var`.
+/// Symbolic when exmex knows the derivative of every operator involved, a
+/// central difference otherwise.
+pub fn derivative(source: &str, var: &str, order: usize) -> String {
+    symbolic(source, var, order).unwrap_or_else(|| numeric(source, var, order))
+}
+
+fn symbolic(source: &str, var: &str, order: usize) -> Option<String> {
+    // exmex only notices a missing derivative deep into differentiating.
+    if tokenize(source).iter().any(|token| {
+        token.kind == TokenKind::Ident && NOT_DIFFERENTIABLE.contains(&&source[token.span.clone()])
+    }) {
+        return None;
+    }
+    let expr = ops::parse(source).ok()?;
+    let Some(index) = expr.var_names().iter().position(|name| name == var) else {
+        return Some("0".to_string());
+    };
+    let derived = expr.partial_nth(index, order).ok()?;
+    // exmex writes every variable in braces, but `var` stays free.
+    let text = derived.unparse().replace(&format!("{{{}}}", var), var);
+    // Numbers are written with `{:?}`, which may not parse back.
+    ops::parse(&text).ok()?;
+    Some(text)
+}
+
+/// Central difference of `order` with binomial weights.
+fn numeric(source: &str, var: &str, order: usize) -> String {
+    let h: f64 = if order == 1 { 1e-5 } else { 1e-3 };
+    let params = [var.to_string()];
+    let mut terms = Vec::new();
+    let mut weight = 1.0;
+    for k in 0..=order {
+        let offset = (order as f64 / 2.0 - k as f64) * h;
+        let shifted = format!("{}+({:.10})", var, offset);
+        terms.push(format!(
+            "({})*({})",
+            weight,
+            substitute(source, &params, &[shifted])
+        ));
+        weight *= -((order - k) as f64) / (k + 1) as f64;
+    }
+    format!("({})/({:.10})^{}", terms.join("+"), h, order)
+}
@@ -0,0 +1,161 @@
+use std::f64::consts::{PI, TAU};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use eframe::egui::{self, ecolor::HsvaGamma, Color32, ColorImage};
+use num_complex::Complex64;
+
+use super::complex::Expr;
+
+/// Opposite corners of a rectangle in world coordinates.
+type Bounds = ((f64, f64), (f64, f64));
+
+/// The function, world rectangle and image size a domain coloring shows.
+#[derive(Clone, PartialEq)]
+struct View {
+    function: Expr,
+    min: (f64, f64),
+    max: (f64, f64),
+    size: [usize; 2],
+    contours: bool,
+}
+
+/// The domain coloring of a complex function as an egui texture, rendered
+/// again on a background thread whenever the function or the view changes.
+/// Until a render is done, the previous one is shown where it was.
+#[derive(Default)]
+pub struct DomainColoring {
+    texture: Option<(egui::TextureHandle, View)>,
+    rendering: Option<(View, Receiver<ColorImage>)>,
+}
+
+impl DomainColoring {
+    /// Asks for the coloring of `function` over the world rectangle
+    /// `min..max` at `size` pixels. Returns the texture to show and the
+    /// corners of the world rectangle it covers.
+    pub fn update(
+        &mut self,
+        ctx: &egui::Context,
+        function: &Expr,
+        (min, max): Bounds,
+        size: [usize; 2],
+        contours: bool,
+    ) -> Option<(egui::TextureId, Bounds)> {
+        if let Some((view, receiver)) = self.rendering.take() {
+            match receiver.try_recv() {
+                Ok(image) => match &mut self.texture {
+                    Some((texture, shown)) => {
+                        texture.set(image, egui::TextureOptions::LINEAR);
+                        *shown = view;
+                    }
+                    None => {
+                        let texture = ctx.load_texture(
+                            "domain coloring",
+                            image,
+                            egui::TextureOptions::LINEAR,
+                        );
+                        self.texture = Some((texture, view));
+                    }
+                },
+                Err(TryRecvError::Empty) => self.rendering = Some((view, receiver)),
+                Err(TryRecvError::Disconnected) => {}
+            }
+        }
+
+        let view = View {
+            function: function.clone(),
+            min,
+            max,
+            size,
+            contours,
+        };
+        let shown = self.texture.as_ref().map(|(_, shown)| shown);
+        if self.rendering.is_none() && shown != Some(&view) {
+            let (sender, receiver) = mpsc::channel();
+            let ctx = ctx.clone();
+            let job = view.clone();
+            thread::spawn(move || {
+                if sender.send(render(&job)).is_ok() {
+                    ctx.request_repaint();
+                }
+            });
+            self.rendering = Some((view, receiver));
+        }
+
+        self.texture
+            .as_ref()
+            .map(|(texture, shown)| (texture.id(), (shown.min, shown.max)))
+    }
+}
+
+/// Colors every pixel by the value of the function at its center, split
+/// into rows rendered in parallel.
+fn render(view: &View) -> ColorImage {
+    let [width, height] = view.size;
+    let mut values = vec![Complex64::default(); width * height];
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let rows = height.div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        for (chunk, values) in values.chunks_mut(rows * width).enumerate() {
+            scope.spawn(move || {
+                for (k, value) in values.iter_mut().enumerate() {
+                    let (row, column) = (chunk * rows + k / width, k % width);
+                    let re = view.min.0
+                        + (column as f64 + 0.5) / width as f64 * (view.max.0 - view.min.0);
+                    let im =
+                        view.max.1 - (row as f64 + 0.5) / height as f64 * (view.max.1 - view.min.1);
+                    *value = view.function.eval(Complex64::new(re, im));
+                }
+            });
+        }
+    });
+
+    let mut pixels: Vec<Color32> = values.iter().map(|&w| color(w)).collect();
+    if view.contours {
+        // A contour runs between neighbouring pixels whose values lie
+        // between different powers of two or twelfths of a turn.
+        let cells: Vec<_> = values
+            .iter()
+            .map(|w| (w.norm().log2().floor(), (w.arg() / (PI / 6.0)).floor()))
+            .collect();
+        for row in 0..height {
+            for column in 0..width {
+                let k = row * width + column;
+                let right = column + 1 < width && cells[k + 1] != cells[k];
+                let below = row + 1 < height && cells[k + width] != cells[k];
+                if (right || below) && values[k].norm().is_finite() {
+                    let [r, g, b, a] = pixels[k].to_array();
+                    pixels[k] = Color32::from_rgba_premultiplied(r / 2, g / 2, b / 2, a);
+                }
+            }
+        }
+    }
+    ColorImage {
+        size: view.size,
+        pixels,
+    }
+}
+
+/// The hue shows the argument of `w`, red on the positive reals, and the
+/// brightness its modulus, from black at zeros to white at poles.
+fn color(w: Complex64) -> Color32 {
+    let modulus = w.norm();
+    if modulus.is_nan() {
+        return Color32::TRANSPARENT;
+    }
+    let hue = (w.arg() / TAU).rem_euclid(1.0) as f32;
+    let lightness = (2.0 / PI * modulus.atan()) as f32;
+    // Lightness as in HSL, so that the colors are brightest at modulus 1.
+    let (s, v) = if lightness < 0.5 {
+        (1.0, 2.0 * lightness)
+    } else {
+        (2.0 * (1.0 - lightness), 1.0)
+    };
+    HsvaGamma {
+        h: hue,
+        s,
+        v,
+        a: 1.0,
+    }
+    .into()
+}
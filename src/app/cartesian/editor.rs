@@ -0,0 +1,393 @@
+use eframe::egui::{
+    self,
+    text::{CCursor, CCursorRange, LayoutJob},
+    Color32, Key, Modifiers, TextFormat,
+};
+
+use super::geometry::Command;
+use super::symbols::{tokenize, SymbolError, TokenKind, CONSTANTS, FUNCTIONS};
+
+/// Names that stand for what a function, curve or equation is evaluated at.
+const FREE: &[&str] = &["x", "y", "t", "r", "z", "i", "θ", "theta"];
+
+/// Most completions offered at once.
+const MAX_COMPLETIONS: usize = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Plain,
+    Number,
+    Operator,
+    Function,
+    Variable,
+    Point,
+    /// Any other defined name, such as a circle or a vector.
+    Object,
+    Free,
+    Error,
+}
+
+/// The names offered for the word the cursor is at the end of, which spans
+/// the characters `start..end`.
+#[derive(Clone, Default)]
+struct Completion {
+    candidates: Vec<(String, &'static str)>,
+    selected: usize,
+    start: usize,
+    end: usize,
+    /// The text the completions were dismissed at with Escape.
+    dismissed: Option<String>,
+}
+
+/// A single line input with syntax highlighting, bracket matching and
+/// completion of built-in and defined names. `names` are the names the lines
+/// define with what each one is, and `error` the error of this line.
+pub fn show(
+    ui: &mut egui::Ui,
+    index: usize,
+    text: &mut String,
+    names: &[(&str, &'static str)],
+    error: Option<&SymbolError>,
+) -> egui::Response {
+    let id = ui.make_persistent_id(("editor", index));
+    let was_focused = ui.memory(|memory| memory.has_focus(id));
+    let mut completion: Completion = ui.data(|data| data.get_temp(id)).unwrap_or_default();
+
+    // The keys the completions use must not reach the text edit, which
+    // would give up focus on Enter, Tab and Escape.
+    if was_focused && !completion.candidates.is_empty() {
+        let count = completion.candidates.len();
+        let pressed = |key| ui.input_mut(|input| input.consume_key(Modifiers::NONE, key));
+        if pressed(Key::ArrowDown) {
+            completion.selected = (completion.selected + 1) % count;
+        }
+        if pressed(Key::ArrowUp) {
+            completion.selected = (completion.selected + count - 1) % count;
+        }
+        if pressed(Key::Escape) {
+            completion.candidates.clear();
+            completion.dismissed = Some(text.clone());
+        } else if pressed(Key::Tab) || pressed(Key::Enter) {
+            accept(ui.ctx(), id, text, &completion);
+            completion.candidates.clear();
+        }
+    }
+
+    let error_at = error.and_then(|error| error.position(text));
+    let undefined = match error {
+        Some(SymbolError::Undefined(names)) => names.clone(),
+        _ => Vec::new(),
+    };
+    let mut layouter = |ui: &egui::Ui, source: &str, wrap_width: f32| {
+        let mut job = highlight(ui, source, names, &undefined, error_at);
+        job.wrap.max_width = wrap_width;
+        ui.fonts(|fonts| fonts.layout_job(job))
+    };
+    let output = egui::TextEdit::singleline(text)
+        .id(id)
+        .font(egui::TextStyle::Monospace)
+        .layouter(&mut layouter)
+        .show(ui);
+    let mut response = output.response;
+    let cursor = output
+        .cursor_range
+        .filter(|range| range.is_empty() && response.has_focus())
+        .map(|range| range.primary.ccursor.index);
+
+    if let Some(cursor) = cursor {
+        let painter = ui.painter_at(output.text_clip_rect);
+        let offset = output.galley_pos.to_vec2();
+        if let Some((at, partner)) = matching_brackets(text, cursor) {
+            let color = if partner.is_some() {
+                ui.visuals().weak_text_color()
+            } else {
+                ui.visuals().error_fg_color
+            };
+            for i in std::iter::once(at).chain(partner) {
+                let rect = egui::Rect::from_min_max(
+                    output.galley.pos_from_ccursor(CCursor::new(i)).min,
+                    output.galley.pos_from_ccursor(CCursor::new(i + 1)).max,
+                );
+                painter.rect_stroke(rect.translate(offset), 2.0, egui::Stroke::new(1.0, color));
+            }
+        }
+    }
+
+    let candidates = cursor
+        .and_then(|cursor| word_before(text, cursor))
+        .map(|(start, end)| {
+            (
+                start,
+                end,
+                complete(&text[byte(text, start)..byte(text, end)], names),
+            )
+        })
+        .filter(|_| completion.dismissed.as_ref() != Some(text));
+    match candidates {
+        Some((start, end, candidates)) => {
+            if candidates != completion.candidates {
+                completion.selected = 0;
+            }
+            completion.candidates = candidates;
+            completion.start = start;
+            completion.end = end;
+            completion.dismissed = None;
+        }
+        None => completion.candidates.clear(),
+    }
+
+    // Clicking a completion takes focus from the text edit before the click
+    // is seen, so the list stays up for as long as either had it.
+    if (was_focused || response.has_focus()) && !completion.candidates.is_empty() {
+        let mut chosen = None;
+        egui::Area::new(id.with("completion"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(response.rect.left_bottom())
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_min_width(response.rect.width());
+                    for (k, (name, kind)) in completion.candidates.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            let label = egui::RichText::new(name).monospace();
+                            if ui
+                                .selectable_label(k == completion.selected, label)
+                                .clicked()
+                            {
+                                chosen = Some(k);
+                            }
+                            ui.weak(*kind);
+                        });
+                    }
+                });
+            });
+        if let Some(k) = chosen {
+            completion.selected = k;
+            accept(ui.ctx(), id, text, &completion);
+            completion.candidates.clear();
+            response.request_focus();
+            response.mark_changed();
+        }
+    }
+
+    ui.data_mut(|data| data.insert_temp(id, completion));
+    response
+}
+
+/// Replaces the word being completed with the selected completion, opening
+/// the call if it is a function, and puts the cursor after it.
+fn accept(ctx: &egui::Context, id: egui::Id, text: &mut String, completion: &Completion) {
+    let (name, kind) = &completion.candidates[completion.selected];
+    let (start, end) = (byte(text, completion.start), byte(text, completion.end));
+    let mut insert = name.clone();
+    if matches!(*kind, "function" | "command") && !text[end..].starts_with('(') {
+        insert.push('(');
+    }
+    text.replace_range(start..end, &insert);
+
+    let cursor = CCursor::new(completion.start + insert.chars().count());
+    if let Some(mut state) = egui::TextEdit::load_state(ctx, id) {
+        state.cursor.set_char_range(Some(CCursorRange::one(cursor)));
+        state.store(ctx, id);
+    }
+}
+
+/// The defined and built-in names that start with `word`, shortest first.
+/// Commands are also offered for a lowercase `word`, completing to their
+/// capitalised name.
+fn complete(word: &str, names: &[(&str, &'static str)]) -> Vec<(String, &'static str)> {
+    let lowercase = word.to_lowercase();
+    let mut candidates: Vec<(String, &'static str)> = names
+        .iter()
+        .map(|&(name, kind)| (name.to_string(), kind))
+        .chain(FUNCTIONS.iter().map(|name| (name.to_string(), "function")))
+        .chain(
+            Command::ALL
+                .iter()
+                .map(|command| (format!("{:?}", command), "command")),
+        )
+        .filter(|(name, kind)| {
+            if *kind == "command" {
+                name.to_lowercase().starts_with(&lowercase) && name != word
+            } else {
+                name.starts_with(word) && name != word
+            }
+        })
+        .collect();
+    candidates.sort_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then(a.cmp(b)));
+    candidates.dedup_by(|(a, _), (b, _)| a == b);
+    candidates.truncate(MAX_COMPLETIONS);
+    candidates
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The characters of the name that ends at the character `cursor`, unless
+/// the cursor is inside a name or after a coordinate access such as `A.x`.
+fn word_before(text: &str, cursor: usize) -> Option<(usize, usize)> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.get(cursor).is_some_and(|&c| is_name_char(c)) {
+        return None;
+    }
+    let mut start = cursor;
+    while start > 0 && is_name_char(chars[start - 1]) {
+        start -= 1;
+    }
+    let is_name =
+        start < cursor && !chars[start].is_ascii_digit() && (start == 0 || chars[start - 1] != '.');
+    is_name.then_some((start, cursor))
+}
+
+/// The bracket right before or at the character `cursor`, and the one it
+/// pairs with if there is one.
+fn matching_brackets(text: &str, cursor: usize) -> Option<(usize, Option<usize>)> {
+    const PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+    let chars: Vec<char> = text.chars().collect();
+    let is_bracket = |i: usize| {
+        chars
+            .get(i)
+            .is_some_and(|&c| PAIRS.iter().any(|&(open, close)| c == open || c == close))
+    };
+    let at = [cursor.checked_sub(1), Some(cursor)]
+        .into_iter()
+        .flatten()
+        .find(|&i| is_bracket(i))?;
+
+    let forward = PAIRS.iter().any(|&(open, _)| chars[at] == open);
+    let mut depth = 0;
+    let mut i = at;
+    loop {
+        let c = chars[i];
+        if PAIRS
+            .iter()
+            .any(|&(open, close)| c == if forward { open } else { close })
+        {
+            depth += 1;
+        } else if PAIRS
+            .iter()
+            .any(|&(open, close)| c == if forward { close } else { open })
+        {
+            depth -= 1;
+        }
+        if depth == 0 {
+            let pair = PAIRS
+                .iter()
+                .any(|&pair| pair == (chars[at], c) || pair == (c, chars[at]));
+            return Some((at, pair.then_some(i)));
+        }
+        i = match forward {
+            true if i + 1 < chars.len() => i + 1,
+            false if i > 0 => i - 1,
+            _ => return Some((at, None)),
+        };
+    }
+}
+
+fn byte(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map_or(text.len(), |(i, _)| i)
+}
+
+/// Colors the tokens of `source`. `error_at` is the byte offset the error
+/// of the line points at, and `undefined` the names it could not find.
+fn highlight(
+    ui: &egui::Ui,
+    source: &str,
+    names: &[(&str, &'static str)],
+    undefined: &[String],
+    error_at: Option<usize>,
+) -> LayoutJob {
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let format = |class: Class| {
+        let color = color(ui.visuals(), class);
+        TextFormat {
+            font_id: font_id.clone(),
+            color,
+            italics: class == Class::Free,
+            underline: if class == Class::Error {
+                egui::Stroke::new(1.0, color)
+            } else {
+                egui::Stroke::NONE
+            },
+            ..Default::default()
+        }
+    };
+
+    let tokens = tokenize(source);
+    let mut job = LayoutJob::default();
+    let mut last = 0;
+    for (k, token) in tokens.iter().enumerate() {
+        job.append(&source[last..token.span.start], 0.0, format(Class::Plain));
+        let word = &source[token.span.clone()];
+        let called = tokens
+            .get(k + 1)
+            .is_some_and(|next| &source[next.span.clone()] == "(");
+        let class = if error_at.is_some_and(|at| token.span.contains(&at)) {
+            Class::Error
+        } else {
+            classify(token.kind, word, called, names, undefined)
+        };
+        job.append(word, 0.0, format(class));
+        last = token.span.end;
+    }
+    job.append(&source[last..], 0.0, format(Class::Plain));
+    job
+}
+
+fn classify(
+    kind: TokenKind,
+    word: &str,
+    called: bool,
+    names: &[(&str, &'static str)],
+    undefined: &[String],
+) -> Class {
+    match kind {
+        TokenKind::Number => Class::Number,
+        TokenKind::Symbol if "+-*/^=<>!|'".contains(word) => Class::Operator,
+        TokenKind::Symbol => Class::Plain,
+        TokenKind::Ident | TokenKind::Member => {
+            let name = word.split('.').next().unwrap_or(word);
+            let defined = names.iter().find(|(defined, _)| *defined == name);
+            if undefined
+                .iter()
+                .any(|undefined| undefined.trim_end_matches("()") == name)
+            {
+                Class::Error
+            } else if let Some((_, kind)) = defined {
+                match *kind {
+                    "variable" | "number" => Class::Variable,
+                    "point" => Class::Point,
+                    "function" | "complex function" => Class::Function,
+                    _ => Class::Object,
+                }
+            } else if called && (FUNCTIONS.contains(&name) || Command::from_name(name).is_some()) {
+                Class::Function
+            } else if CONSTANTS.contains(&name) {
+                Class::Number
+            } else if FREE.contains(&name) {
+                Class::Free
+            } else {
+                Class::Plain
+            }
+        }
+    }
+}
+
+fn color(visuals: &egui::Visuals, class: Class) -> Color32 {
+    let pick = |dark: [u8; 3], light: [u8; 3]| {
+        let [r, g, b] = if visuals.dark_mode { dark } else { light };
+        Color32::from_rgb(r, g, b)
+    };
+    match class {
+        Class::Plain | Class::Free => visuals.text_color(),
+        Class::Number => pick([181, 206, 168], [9, 134, 88]),
+        Class::Operator => pick([212, 180, 120], [150, 90, 0]),
+        Class::Function => pick([220, 220, 170], [121, 94, 38]),
+        Class::Variable => pick([156, 220, 254], [0, 70, 160]),
+        Class::Point => pick([78, 201, 176], [38, 127, 153]),
+        Class::Object => pick([197, 134, 192], [160, 0, 200]),
+        Class::Error => visuals.error_fg_color,
+    }
+}
@@ -0,0 +1,929 @@
+use super::symbols::Value;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Segment((f64, f64), (f64, f64)),
+    /// The line through two points.
+    Line((f64, f64), (f64, f64)),
+    /// The ray from the first point through the second.
+    Ray((f64, f64), (f64, f64)),
+    Circle {
+        center: (f64, f64),
+        radius: f64,
+    },
+    Polygon(Vec<(f64, f64)>),
+    /// Several points, such as all intersections of two objects.
+    Points(Vec<(f64, f64)>),
+    /// Several lines, each through two points, such as the tangents to a
+    /// circle from a point.
+    Lines(Vec<((f64, f64), (f64, f64))>),
+    /// The points `center + cos t·a + sin t·b` for conjugate semi-axes `a`
+    /// and `b`, which a circle turns into under a general affine map.
+    Ellipse {
+        center: (f64, f64),
+        axes: [(f64, f64); 2],
+    },
+    /// The graph of the function among a command's arguments, mapped by an
+    /// affine map.
+    Graph(Affine),
+}
+
+/// The affine map `p ↦ m·p + b`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Affine {
+    pub m: [[f64; 2]; 2],
+    pub b: (f64, f64),
+}
+
+impl Affine {
+    fn linear(m: [[f64; 2]; 2], fixed: (f64, f64)) -> Self {
+        let moved = Affine { m, b: (0.0, 0.0) }.apply(fixed);
+        Affine {
+            m,
+            b: (fixed.0 - moved.0, fixed.1 - moved.1),
+        }
+    }
+
+    fn translation((dx, dy): (f64, f64)) -> Self {
+        Affine {
+            m: [[1.0, 0.0], [0.0, 1.0]],
+            b: (dx, dy),
+        }
+    }
+
+    fn rotation(angle: f64, center: (f64, f64)) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Affine::linear([[cos, -sin], [sin, cos]], center)
+    }
+
+    fn dilation(k: f64, center: (f64, f64)) -> Self {
+        Affine::linear([[k, 0.0], [0.0, k]], center)
+    }
+
+    /// The reflection in the line through `p` with direction `d`.
+    fn reflection(p: (f64, f64), d: (f64, f64)) -> Option<Self> {
+        let (ux, uy) = unit((0.0, 0.0), d)?;
+        let m = [
+            [ux * ux - uy * uy, 2.0 * ux * uy],
+            [2.0 * ux * uy, uy * uy - ux * ux],
+        ];
+        Some(Affine::linear(m, p))
+    }
+
+    pub fn apply(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let [[a, b], [c, d]] = self.m;
+        (a * x + b * y + self.b.0, c * x + d * y + self.b.1)
+    }
+
+    fn vector(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let [[a, b], [c, d]] = self.m;
+        (a * x + b * y, c * x + d * y)
+    }
+
+    /// This map followed by `next`.
+    fn then(&self, next: &Affine) -> Affine {
+        let column = |k: usize| next.vector((self.m[0][k], self.m[1][k]));
+        let (first, second) = (column(0), column(1));
+        Affine {
+            m: [[first.0, second.0], [first.1, second.1]],
+            b: next.apply(self.b),
+        }
+    }
+
+    pub fn inverse(&self) -> Option<Affine> {
+        let [[a, b], [c, d]] = self.m;
+        let determinant = a * d - b * c;
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+        let m = [
+            [d / determinant, -b / determinant],
+            [-c / determinant, a / determinant],
+        ];
+        let moved = Affine { m, b: (0.0, 0.0) }.apply(self.b);
+        Some(Affine {
+            m,
+            b: (-moved.0, -moved.1),
+        })
+    }
+
+    /// How much the map stretches lengths at most, roughly.
+    pub fn scale(&self) -> f64 {
+        let [[a, b], [c, d]] = self.m;
+        a.hypot(c).max(b.hypot(d))
+    }
+}
+
+/// An object argument of a command.
+#[derive(Clone, Copy)]
+pub enum Object<'a> {
+    Point((f64, f64)),
+    Shape(&'a Shape),
+    /// The function among the command's arguments.
+    Graph,
+}
+
+/// A piece of an object that intersections are found with: the points
+/// `p + t·d` for `t` in the range, or a circle.
+enum Curve {
+    Line {
+        p: (f64, f64),
+        d: (f64, f64),
+        range: (f64, f64),
+    },
+    Circle {
+        center: (f64, f64),
+        radius: f64,
+    },
+}
+
+impl Shape {
+    fn transform(&self, map: &Affine) -> Shape {
+        let apply = |p: &(f64, f64)| map.apply(*p);
+        match self {
+            Shape::Segment(a, b) => Shape::Segment(apply(a), apply(b)),
+            Shape::Line(a, b) => Shape::Line(apply(a), apply(b)),
+            Shape::Ray(a, b) => Shape::Ray(apply(a), apply(b)),
+            &Shape::Circle { center, radius } => Shape::Ellipse {
+                center,
+                axes: [(radius, 0.0), (0.0, radius)],
+            }
+            .transform(map),
+            Shape::Polygon(points) => Shape::Polygon(points.iter().map(apply).collect()),
+            Shape::Points(points) => Shape::Points(points.iter().map(apply).collect()),
+            Shape::Lines(lines) => {
+                Shape::Lines(lines.iter().map(|(a, b)| (apply(a), apply(b))).collect())
+            }
+            Shape::Ellipse { center, axes } => {
+                let [a, b] = axes.map(|axis| map.vector(axis));
+                let (ra, rb) = (a.0.hypot(a.1), b.0.hypot(b.1));
+                let round = (ra - rb).abs() <= 1e-12 * ra.max(rb)
+                    && (a.0 * b.0 + a.1 * b.1).abs() <= 1e-12 * ra * rb;
+                if round {
+                    Shape::Circle {
+                        center: apply(center),
+                        radius: ra,
+                    }
+                } else {
+                    Shape::Ellipse {
+                        center: apply(center),
+                        axes: [a, b],
+                    }
+                }
+            }
+            Shape::Graph(first) => Shape::Graph(first.then(map)),
+        }
+    }
+
+    /// The point and direction of a segment, line or ray.
+    fn direction(&self) -> Option<((f64, f64), (f64, f64))> {
+        match *self {
+            Shape::Segment(a, b) | Shape::Line(a, b) | Shape::Ray(a, b) => {
+                Some((a, (b.0 - a.0, b.1 - a.1)))
+            }
+            _ => None,
+        }
+    }
+
+    fn curves(&self) -> Vec<Curve> {
+        let line = |a: (f64, f64), b: (f64, f64), range| Curve::Line {
+            p: a,
+            d: (b.0 - a.0, b.1 - a.1),
+            range,
+        };
+        let all = f64::INFINITY;
+        match self {
+            Shape::Segment(a, b) => vec![line(*a, *b, (0.0, 1.0))],
+            Shape::Line(a, b) => vec![line(*a, *b, (-all, all))],
+            Shape::Ray(a, b) => vec![line(*a, *b, (0.0, all))],
+            &Shape::Circle { center, radius } => vec![Curve::Circle { center, radius }],
+            Shape::Polygon(points) => (0..points.len())
+                .map(|k| line(points[k], points[(k + 1) % points.len()], (0.0, 1.0)))
+                .collect(),
+            Shape::Points(_) | Shape::Graph(_) => Vec::new(),
+            &Shape::Ellipse { center, axes } => {
+                let outline = ellipse(center, axes, 64);
+                outline
+                    .windows(2)
+                    .map(|pair| line(pair[0], pair[1], (0.0, 1.0)))
+                    .collect()
+            }
+            Shape::Lines(lines) => lines
+                .iter()
+                .map(|&(a, b)| line(a, b, (-all, all)))
+                .collect(),
+        }
+    }
+}
+
+/// A geometric command such as `Segment(A, B)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Segment,
+    Line,
+    Ray,
+    Circle,
+    Polygon,
+    Midpoint,
+    Perpendicular,
+    Parallel,
+    AngleBisector,
+    PerpendicularBisector,
+    Intersect,
+    Tangents,
+    Translate,
+    Rotate,
+    Reflect,
+    Dilate,
+    Transform,
+    Distance,
+    Angle,
+    Area,
+    Perimeter,
+}
+
+/// What a command argument has to be.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parameter {
+    Point,
+    Number,
+    /// The name of another object.
+    Shape,
+    /// The name of a point, object or function, or an expression in `x`.
+    Object,
+    /// A matrix such as `((0, -1), (1, 0))`, or `((1, 0, 2), (0, 1, 3))` with a
+    /// third column that is added.
+    Matrix,
+}
+
+impl Command {
+    pub const ALL: [Command; 21] = [
+        Command::Segment,
+        Command::Line,
+        Command::Ray,
+        Command::Circle,
+        Command::Polygon,
+        Command::Midpoint,
+        Command::Perpendicular,
+        Command::Parallel,
+        Command::AngleBisector,
+        Command::PerpendicularBisector,
+        Command::Intersect,
+        Command::Tangents,
+        Command::Translate,
+        Command::Rotate,
+        Command::Reflect,
+        Command::Dilate,
+        Command::Transform,
+        Command::Distance,
+        Command::Angle,
+        Command::Area,
+        Command::Perimeter,
+    ];
+
+    /// The command written exactly as `name`, capitalised like `Segment`, so
+    /// that it doesn't take over a user function such as `area(x)`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|command| format!("{:?}", command) == name)
+    }
+
+    pub fn kind(self) -> &'static str {
+        match self {
+            Command::Segment => "segment",
+            Command::Line => "line",
+            Command::Ray => "ray",
+            Command::Circle => "circle",
+            Command::Polygon => "polygon",
+            Command::Midpoint => "midpoint",
+            Command::Perpendicular => "perpendicular",
+            Command::Parallel => "parallel",
+            Command::AngleBisector => "anglebisector",
+            Command::PerpendicularBisector => "perpendicularbisector",
+            Command::Intersect => "intersect",
+            Command::Tangents => "tangents",
+            Command::Translate => "translate",
+            Command::Rotate => "rotate",
+            Command::Reflect => "reflect",
+            Command::Dilate => "dilate",
+            Command::Transform => "transform",
+            Command::Distance => "distance",
+            Command::Angle => "angle",
+            Command::Area => "area",
+            Command::Perimeter => "perimeter",
+        }
+    }
+
+    pub fn is_transform(self) -> bool {
+        matches!(
+            self,
+            Command::Translate
+                | Command::Rotate
+                | Command::Reflect
+                | Command::Dilate
+                | Command::Transform
+        )
+    }
+
+    /// `"point"` or `"number"` if the command makes a single point or a
+    /// measurement out of arguments with `numbers` numeric parts, rather than
+    /// an object.
+    pub fn value_kind(self, numbers: usize) -> Option<&'static str> {
+        match self {
+            Command::Midpoint => Some("point"),
+            Command::Intersect if numbers == 1 => Some("point"),
+            Command::Distance | Command::Angle | Command::Area | Command::Perimeter => {
+                Some("number")
+            }
+            _ => None,
+        }
+    }
+
+    /// What each of `count` arguments has to be, or `None` if the command
+    /// does not take that many.
+    pub fn parameters(self, count: usize) -> Option<Vec<Parameter>> {
+        use Parameter::{Matrix, Number, Object, Point, Shape};
+        match (self, count) {
+            (
+                Command::Segment
+                | Command::Line
+                | Command::Ray
+                | Command::Midpoint
+                | Command::PerpendicularBisector
+                | Command::Distance,
+                2,
+            ) => Some(vec![Point, Point]),
+            (Command::Circle, 2) => Some(vec![Point, Number]),
+            (Command::Circle | Command::AngleBisector | Command::Angle, 3) => {
+                Some(vec![Point, Point, Point])
+            }
+            (Command::Polygon, 3..) => Some(vec![Point; count]),
+            (Command::Midpoint | Command::Area | Command::Perimeter, 1) => Some(vec![Shape]),
+            (Command::Perpendicular | Command::Parallel | Command::Tangents, 2) => {
+                Some(vec![Shape, Point])
+            }
+            (Command::Intersect, 2) => Some(vec![Shape, Shape]),
+            (Command::Intersect, 3) => Some(vec![Shape, Shape, Number]),
+            (Command::Translate, 2) => Some(vec![Object, Point]),
+            (Command::Rotate | Command::Dilate, 2) => Some(vec![Object, Number]),
+            (Command::Rotate | Command::Dilate, 3) => Some(vec![Object, Number, Point]),
+            (Command::Reflect, 2) => Some(vec![Object, Shape]),
+            (Command::Transform, 2) => Some(vec![Object, Matrix]),
+            _ => None,
+        }
+    }
+
+    /// Builds the object from its arguments, with every point flattened into
+    /// its two coordinates and the objects it refers to in `shapes`.
+    pub fn evaluate(self, args: &[f64], objects: &[Object]) -> Option<Value> {
+        if self.is_transform() {
+            return self.transform(args, objects);
+        }
+        let points: Vec<(f64, f64)> = args.chunks_exact(2).map(|p| (p[0], p[1])).collect();
+        let shape = match (self, args, objects) {
+            (Command::Segment, _, _) => Shape::Segment(points[0], points[1]),
+            (Command::Line, _, _) if points[0] != points[1] => Shape::Line(points[0], points[1]),
+            (Command::Ray, _, _) if points[0] != points[1] => Shape::Ray(points[0], points[1]),
+            (Command::Circle, &[x, y, radius], _) if radius >= 0.0 => Shape::Circle {
+                center: (x, y),
+                radius,
+            },
+            (Command::Circle, &[_, _, _, _, _, _], _) => {
+                let (center, radius) = circumcircle(points[0], points[1], points[2])?;
+                Shape::Circle { center, radius }
+            }
+            (Command::Polygon, _, _) => Shape::Polygon(points),
+            (Command::Midpoint, &[], &[Object::Shape(shape)]) => {
+                let (x, y) = match *shape {
+                    Shape::Segment(a, b) => midpoint(a, b),
+                    Shape::Circle { center, .. } | Shape::Ellipse { center, .. } => center,
+                    _ => return None,
+                };
+                return Some(Value::Point(x, y));
+            }
+            (Command::Midpoint, _, _) => {
+                let (x, y) = midpoint(points[0], points[1]);
+                return Some(Value::Point(x, y));
+            }
+            (Command::Perpendicular, _, &[Object::Shape(line)]) => {
+                let (_, (dx, dy)) = line.direction()?;
+                let p = points[0];
+                Shape::Line(p, (p.0 - dy, p.1 + dx))
+            }
+            (Command::Parallel, _, &[Object::Shape(line)]) => {
+                let (_, (dx, dy)) = line.direction()?;
+                let p = points[0];
+                Shape::Line(p, (p.0 + dx, p.1 + dy))
+            }
+            (Command::AngleBisector, _, _) => {
+                let [a, b, c] = [points[0], points[1], points[2]];
+                let (u, v) = (unit(b, a)?, unit(b, c)?);
+                let (dx, dy) = (u.0 + v.0, u.1 + v.1);
+                // A straight angle is bisected by the perpendicular.
+                let (dx, dy) = if dx.hypot(dy) < 1e-12 {
+                    (-u.1, u.0)
+                } else {
+                    (dx, dy)
+                };
+                Shape::Line(b, (b.0 + dx, b.1 + dy))
+            }
+            (Command::PerpendicularBisector, _, _) if points[0] != points[1] => {
+                let [a, b] = [points[0], points[1]];
+                let m = midpoint(a, b);
+                Shape::Line(m, (m.0 - (b.1 - a.1), m.1 + (b.0 - a.0)))
+            }
+            (Command::Intersect, _, &[Object::Shape(first), Object::Shape(second)]) => {
+                let mut found = Vec::new();
+                for p in first.curves() {
+                    for q in second.curves() {
+                        for point in intersections(&p, &q) {
+                            let new = found
+                                .iter()
+                                .all(|f: &(f64, f64)| (f.0 - point.0).hypot(f.1 - point.1) > 1e-9);
+                            if new {
+                                found.push(point);
+                            }
+                        }
+                    }
+                }
+                match args {
+                    // Intersections are counted from 1.
+                    &[n] => {
+                        let (x, y) = *found.get((n.round() as usize).checked_sub(1)?)?;
+                        return Some(Value::Point(x, y));
+                    }
+                    // Without an index, a single intersection is a point.
+                    _ => match found[..] {
+                        [(x, y)] => return Some(Value::Point(x, y)),
+                        _ => Shape::Points(found),
+                    },
+                }
+            }
+            (Command::Tangents, _, &[Object::Shape(&Shape::Circle { center, radius })]) => {
+                Shape::Lines(tangents(center, radius, points[0]))
+            }
+            (Command::Distance, _, _) => {
+                let [a, b] = [points[0], points[1]];
+                return Some(Value::Number((b.0 - a.0).hypot(b.1 - a.1)));
+            }
+            (Command::Angle, _, _) => {
+                return angle(points[0], points[1], points[2]).map(Value::Number);
+            }
+            (Command::Area, _, &[Object::Shape(shape)]) => {
+                let area = match shape {
+                    Shape::Polygon(points) => {
+                        let twice: f64 = (0..points.len())
+                            .map(|k| cross((0.0, 0.0), points[k], points[(k + 1) % points.len()]))
+                            .sum();
+                        twice.abs() / 2.0
+                    }
+                    Shape::Circle { radius, .. } => std::f64::consts::PI * radius * radius,
+                    &Shape::Ellipse { axes: [a, b], .. } => {
+                        std::f64::consts::PI * cross((0.0, 0.0), a, b).abs()
+                    }
+                    _ => return None,
+                };
+                return Some(Value::Number(area));
+            }
+            (Command::Perimeter, _, &[Object::Shape(shape)]) => {
+                let length = |points: &[(f64, f64)]| {
+                    (0..points.len())
+                        .map(|k| {
+                            let (a, b) = (points[k], points[(k + 1) % points.len()]);
+                            (b.0 - a.0).hypot(b.1 - a.1)
+                        })
+                        .sum()
+                };
+                let perimeter = match shape {
+                    Shape::Polygon(points) => length(points),
+                    Shape::Circle { radius, .. } => std::f64::consts::TAU * radius,
+                    &Shape::Ellipse { center, axes } => length(&ellipse(center, axes, 1024)),
+                    _ => return None,
+                };
+                return Some(Value::Number(perimeter));
+            }
+            _ => return None,
+        };
+        Some(Value::Shape(shape))
+    }
+
+    /// Applies a transformation to its first argument, which may be a point,
+    /// an object or a function graph.
+    fn transform(self, args: &[f64], objects: &[Object]) -> Option<Value> {
+        let origin = (0.0, 0.0);
+        let map = match (self, args, objects) {
+            (Command::Translate, &[dx, dy], _) => Affine::translation((dx, dy)),
+            (Command::Rotate, &[angle], _) => Affine::rotation(angle, origin),
+            (Command::Rotate, &[angle, x, y], _) => Affine::rotation(angle, (x, y)),
+            (Command::Dilate, &[k], _) => Affine::dilation(k, origin),
+            (Command::Dilate, &[k, x, y], _) => Affine::dilation(k, (x, y)),
+            (Command::Reflect, _, &[_, Object::Shape(line)]) => {
+                let (p, d) = line.direction()?;
+                Affine::reflection(p, d)?
+            }
+            (Command::Transform, &[a, b, e, c, d, f], _) => Affine {
+                m: [[a, b], [c, d]],
+                b: (e, f),
+            },
+            _ => return None,
+        };
+        Some(match objects.first()? {
+            &Object::Point(p) => {
+                let (x, y) = map.apply(p);
+                Value::Point(x, y)
+            }
+            Object::Shape(shape) => Value::Shape(shape.transform(&map)),
+            Object::Graph => Value::Shape(Shape::Graph(map)),
+        })
+    }
+}
+
+/// Points around an ellipse, with the first repeated at the end.
+pub fn ellipse(center: (f64, f64), [a, b]: [(f64, f64); 2], steps: usize) -> Vec<(f64, f64)> {
+    (0..=steps)
+        .map(|k| {
+            let (sin, cos) = (std::f64::consts::TAU * k as f64 / steps as f64).sin_cos();
+            (
+                center.0 + cos * a.0 + sin * b.0,
+                center.1 + cos * a.1 + sin * b.1,
+            )
+        })
+        .collect()
+}
+
+/// The angle at `b` from `a` counterclockwise to `c`, in radians between 0
+/// and 2π.
+pub fn angle(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> Option<f64> {
+    let (u, v) = (unit(b, a)?, unit(b, c)?);
+    let angle = (u.0 * v.1 - u.1 * v.0).atan2(u.0 * v.0 + u.1 * v.1);
+    Some(angle.rem_euclid(std::f64::consts::TAU))
+}
+
+fn midpoint(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)
+}
+
+/// The unit vector from `from` towards `to`, unless they coincide.
+fn unit(from: (f64, f64), to: (f64, f64)) -> Option<(f64, f64)> {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = dx.hypot(dy);
+    (length > 0.0).then(|| (dx / length, dy / length))
+}
+
+/// Where two curves meet. Parallel lines never do, even if they overlap.
+fn intersections(p: &Curve, q: &Curve) -> Vec<(f64, f64)> {
+    let epsilon = 1e-9;
+    let within = |t: f64, (lo, hi): (f64, f64)| t >= lo - epsilon && t <= hi + epsilon;
+    match (p, q) {
+        (
+            &Curve::Line { p: a, d, range },
+            &Curve::Line {
+                p: b,
+                d: e,
+                range: other,
+            },
+        ) => {
+            let denominator = d.0 * e.1 - d.1 * e.0;
+            if denominator.abs() < 1e-12 {
+                return Vec::new();
+            }
+            let (wx, wy) = (b.0 - a.0, b.1 - a.1);
+            let t = (wx * e.1 - wy * e.0) / denominator;
+            let s = (wx * d.1 - wy * d.0) / denominator;
+            if within(t, range) && within(s, other) {
+                vec![(a.0 + t * d.0, a.1 + t * d.1)]
+            } else {
+                Vec::new()
+            }
+        }
+        (&Curve::Line { p, d, range }, &Curve::Circle { center, radius })
+        | (&Curve::Circle { center, radius }, &Curve::Line { p, d, range }) => {
+            let (wx, wy) = (p.0 - center.0, p.1 - center.1);
+            let a = d.0 * d.0 + d.1 * d.1;
+            let b = 2.0 * (wx * d.0 + wy * d.1);
+            let c = wx * wx + wy * wy - radius * radius;
+            let discriminant = b * b - 4.0 * a * c;
+            if a == 0.0 || discriminant < -epsilon {
+                return Vec::new();
+            }
+            let root = discriminant.max(0.0).sqrt();
+            let mut ts = vec![(-b - root) / (2.0 * a)];
+            if root > 0.0 {
+                ts.push((-b + root) / (2.0 * a));
+            }
+            ts.into_iter()
+                .filter(|&t| within(t, range))
+                .map(|t| (p.0 + t * d.0, p.1 + t * d.1))
+                .collect()
+        }
+        (
+            &Curve::Circle {
+                center: c1,
+                radius: r1,
+            },
+            &Curve::Circle {
+                center: c2,
+                radius: r2,
+            },
+        ) => {
+            let distance = (c2.0 - c1.0).hypot(c2.1 - c1.1);
+            if distance == 0.0 || distance > r1 + r2 || distance < (r1 - r2).abs() {
+                return Vec::new();
+            }
+            let along = (distance * distance + r1 * r1 - r2 * r2) / (2.0 * distance);
+            let across = (r1 * r1 - along * along).max(0.0).sqrt();
+            let (ux, uy) = ((c2.0 - c1.0) / distance, (c2.1 - c1.1) / distance);
+            let foot = (c1.0 + along * ux, c1.1 + along * uy);
+            let mut points = vec![(foot.0 - across * uy, foot.1 + across * ux)];
+            if across > 0.0 {
+                points.push((foot.0 + across * uy, foot.1 - across * ux));
+            }
+            points
+        }
+    }
+}
+
+/// The tangents to a circle through `p`: none from inside, one from on the
+/// circle and two from outside.
+fn tangents(center: (f64, f64), radius: f64, p: (f64, f64)) -> Vec<((f64, f64), (f64, f64))> {
+    let (dx, dy) = (p.0 - center.0, p.1 - center.1);
+    let distance = dx.hypot(dy);
+    if distance < radius || distance == 0.0 {
+        return Vec::new();
+    }
+    if distance - radius < 1e-12 * radius.max(1.0) {
+        return vec![(p, (p.0 - dy, p.1 + dx))];
+    }
+    let direction = dy.atan2(dx);
+    let spread = (radius / distance).acos();
+    [direction - spread, direction + spread]
+        .into_iter()
+        .map(|angle| {
+            let touch = (
+                center.0 + radius * angle.cos(),
+                center.1 + radius * angle.sin(),
+            );
+            (p, touch)
+        })
+        .collect()
+}
+
+/// The circle through three points, unless they are on one line.
+fn circumcircle(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> Option<((f64, f64), f64)> {
+    let d = 2.0 * (a.0 * (b.1 - c.1) + b.0 * (c.1 - a.1) + c.0 * (a.1 - b.1));
+    if d.abs() < 1e-12 {
+        return None;
+    }
+    let (la, lb, lc) = (
+        a.0 * a.0 + a.1 * a.1,
+        b.0 * b.0 + b.1 * b.1,
+        c.0 * c.0 + c.1 * c.1,
+    );
+    let center = (
+        (la * (b.1 - c.1) + lb * (c.1 - a.1) + lc * (a.1 - b.1)) / d,
+        (la * (c.0 - b.0) + lb * (a.0 - c.0) + lc * (b.0 - a.0)) / d,
+    );
+    Some((center, (a.0 - center.0).hypot(a.1 - center.1)))
+}
+
+fn cross(o: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+/// Splits a simple polygon into triangles by ear clipping. A polygon that
+/// crosses itself is only split as far as it can be.
+pub fn triangulate(polygon: &[(f64, f64)]) -> Vec<[usize; 3]> {
+    let twice_area: f64 = (0..polygon.len())
+        .map(|k| cross((0.0, 0.0), polygon[k], polygon[(k + 1) % polygon.len()]))
+        .sum();
+    let orientation = twice_area.signum();
+
+    let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+    let mut triangles = Vec::new();
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let ear = (0..m).find(|&k| {
+            let (a, b, c) = (
+                remaining[(k + m - 1) % m],
+                remaining[k],
+                remaining[(k + 1) % m],
+            );
+            let (pa, pb, pc) = (polygon[a], polygon[b], polygon[c]);
+            cross(pa, pb, pc) * orientation > 0.0
+                && !remaining.iter().any(|&p| {
+                    p != a
+                        && p != b
+                        && p != c
+                        && cross(pa, pb, polygon[p]) * orientation >= 0.0
+                        && cross(pb, pc, polygon[p]) * orientation >= 0.0
+                        && cross(pc, pa, polygon[p]) * orientation >= 0.0
+                })
+        });
+        let Some(k) = ear else {
+            return triangles;
+        };
+        triangles.push([
+            remaining[(k + m - 1) % m],
+            remaining[k],
+            remaining[(k + 1) % m],
+        ]);
+        remaining.remove(k);
+    }
+    if let [a, b, c] = remaining[..] {
+        triangles.push([a, b, c]);
+    }
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(p: (f64, f64), q: (f64, f64)) -> bool {
+        (p.0 - q.0).hypot(p.1 - q.1) < 1e-9
+    }
+
+    fn line(p: (f64, f64), d: (f64, f64)) -> Curve {
+        Curve::Line {
+            p,
+            d,
+            range: (f64::NEG_INFINITY, f64::INFINITY),
+        }
+    }
+
+    #[test]
+    fn circumcircle_passes_through_the_points() {
+        let (center, radius) = circumcircle((1.0, 0.0), (0.0, 1.0), (-1.0, 0.0)).unwrap();
+        assert!(close(center, (0.0, 0.0)));
+        assert!((radius - 1.0).abs() < 1e-9);
+        assert_eq!(circumcircle((0.0, 0.0), (1.0, 1.0), (2.0, 2.0)), None);
+    }
+
+    #[test]
+    fn lines_meet_once_unless_parallel() {
+        let found = intersections(
+            &line((0.0, 0.0), (1.0, 1.0)),
+            &line((0.0, 2.0), (1.0, -1.0)),
+        );
+        assert!(matches!(found[..], [p] if close(p, (1.0, 1.0))));
+        let parallel = intersections(&line((0.0, 0.0), (1.0, 1.0)), &line((0.0, 1.0), (2.0, 2.0)));
+        assert!(parallel.is_empty());
+        // A segment ends before the other line.
+        let segment = Curve::Line {
+            p: (0.0, 0.0),
+            d: (1.0, 1.0),
+            range: (0.0, 0.5),
+        };
+        assert!(intersections(&segment, &line((0.0, 2.0), (1.0, -1.0))).is_empty());
+    }
+
+    #[test]
+    fn lines_cross_or_touch_circles() {
+        let circle = Curve::Circle {
+            center: (0.0, 0.0),
+            radius: 1.0,
+        };
+        let across = intersections(&line((-2.0, 0.0), (1.0, 0.0)), &circle);
+        assert_eq!(across.len(), 2);
+        assert!(across.iter().any(|&p| close(p, (-1.0, 0.0))));
+        assert!(across.iter().any(|&p| close(p, (1.0, 0.0))));
+        let touching = intersections(&circle, &line((0.0, 1.0), (1.0, 0.0)));
+        assert!(matches!(touching[..], [p] if close(p, (0.0, 1.0))));
+        assert!(intersections(&line((0.0, 2.0), (1.0, 0.0)), &circle).is_empty());
+    }
+
+    #[test]
+    fn circles_cross_or_touch() {
+        let unit = Curve::Circle {
+            center: (0.0, 0.0),
+            radius: 1.0,
+        };
+        let crossing = Curve::Circle {
+            center: (1.0, 0.0),
+            radius: 1.0,
+        };
+        let found = intersections(&unit, &crossing);
+        let h = 3f64.sqrt() / 2.0;
+        assert_eq!(found.len(), 2);
+        assert!(found.iter().any(|&p| close(p, (0.5, h))));
+        assert!(found.iter().any(|&p| close(p, (0.5, -h))));
+        let outside = Curve::Circle {
+            center: (2.0, 0.0),
+            radius: 1.0,
+        };
+        assert!(matches!(intersections(&unit, &outside)[..], [p] if close(p, (1.0, 0.0))));
+        let inside = Curve::Circle {
+            center: (0.5, 0.0),
+            radius: 0.5,
+        };
+        assert!(matches!(intersections(&unit, &inside)[..], [p] if close(p, (1.0, 0.0))));
+        let apart = Curve::Circle {
+            center: (3.0, 0.0),
+            radius: 1.0,
+        };
+        assert!(intersections(&unit, &apart).is_empty());
+    }
+
+    #[test]
+    fn tangents_depend_on_where_the_point_is() {
+        assert!(tangents((0.0, 0.0), 1.0, (0.5, 0.0)).is_empty());
+        let on = tangents((0.0, 0.0), 1.0, (1.0, 0.0));
+        assert!(matches!(on[..], [(p, q)] if close(p, (1.0, 0.0)) && close(q, (1.0, 1.0))));
+        let from = (2.0, 0.0);
+        let outside = tangents((0.0, 0.0), 1.0, from);
+        assert_eq!(outside.len(), 2);
+        for (p, touch) in outside {
+            assert!(close(p, from));
+            assert!((touch.0.hypot(touch.1) - 1.0).abs() < 1e-9);
+            // The radius to the touching point is perpendicular to the tangent.
+            let along = (from.0 - touch.0) * touch.0 + (from.1 - touch.1) * touch.1;
+            assert!(along.abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn polygons_split_into_triangles() {
+        let square = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        assert_eq!(triangulate(&square).len(), 2);
+        // A concave arrowhead, clockwise.
+        let arrow = [(0.0, 0.0), (1.0, 2.0), (2.0, 0.0), (1.0, 1.0)];
+        let triangles = triangulate(&arrow);
+        assert_eq!(triangles.len(), 2);
+        let area: f64 = triangles
+            .iter()
+            .map(|&[a, b, c]| cross(arrow[a], arrow[b], arrow[c]).abs() / 2.0)
+            .sum();
+        assert!((area - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn angles_go_counterclockwise() {
+        let right = angle((1.0, 0.0), (0.0, 0.0), (0.0, 1.0)).unwrap();
+        assert!((right - std::f64::consts::FRAC_PI_2).abs() < 1e-12);
+        let reflex = angle((0.0, 1.0), (0.0, 0.0), (1.0, 0.0)).unwrap();
+        assert!((reflex - 3.0 * std::f64::consts::FRAC_PI_2).abs() < 1e-12);
+        assert_eq!(angle((0.0, 0.0), (0.0, 0.0), (1.0, 0.0)), None);
+    }
+
+    fn measure(command: Command, shape: &Shape) -> f64 {
+        let Some(Value::Number(value)) = command.evaluate(&[], &[Object::Shape(shape)]) else {
+            panic!("{:?} is not a number", command);
+        };
+        value
+    }
+
+    #[test]
+    fn polygons_are_measured_either_way_round() {
+        let square = vec![(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)];
+        let clockwise: Vec<_> = square.iter().rev().copied().collect();
+        for points in [square, clockwise] {
+            let polygon = Shape::Polygon(points);
+            assert_eq!(measure(Command::Area, &polygon), 4.0);
+            assert_eq!(measure(Command::Perimeter, &polygon), 8.0);
+        }
+    }
+
+    #[test]
+    fn ellipses_are_measured() {
+        let circle = Shape::Ellipse {
+            center: (1.0, 1.0),
+            axes: [(2.0, 0.0), (0.0, 2.0)],
+        };
+        let pi = std::f64::consts::PI;
+        assert!((measure(Command::Area, &circle) - 4.0 * pi).abs() < 1e-9);
+        assert!((measure(Command::Perimeter, &circle) - 4.0 * pi).abs() < 1e-3);
+        // Conjugate semi-axes that are not perpendicular.
+        let ellipse = Shape::Ellipse {
+            center: (0.0, 0.0),
+            axes: [(2.0, 0.0), (1.0, 1.0)],
+        };
+        assert!((measure(Command::Area, &ellipse) - 2.0 * pi).abs() < 1e-9);
+    }
+
+    #[test]
+    fn maps_compose_in_order() {
+        let rotate = Affine::rotation(std::f64::consts::FRAC_PI_2, (0.0, 0.0));
+        let shift = Affine::translation((1.0, 0.0));
+        assert!(close(rotate.then(&shift).apply((1.0, 0.0)), (1.0, 1.0)));
+        assert!(close(shift.then(&rotate).apply((1.0, 0.0)), (0.0, 2.0)));
+        let reflect = Affine::reflection((0.0, 1.0), (1.0, 0.0)).unwrap();
+        let twice = reflect.then(&reflect);
+        for p in [(0.0, 0.0), (3.0, -2.0)] {
+            assert!(close(twice.apply(p), p));
+        }
+    }
+
+    #[test]
+    fn inverses_undo_maps() {
+        let map = Affine::rotation(0.7, (1.0, 2.0))
+            .then(&Affine::dilation(3.0, (-1.0, 0.5)))
+            .then(&Affine::translation((4.0, -1.0)));
+        let inverse = map.inverse().unwrap();
+        for p in [(0.0, 0.0), (2.5, -1.5)] {
+            assert!(close(inverse.apply(map.apply(p)), p));
+            assert!(close(map.apply(inverse.apply(p)), p));
+        }
+        assert_eq!(Affine::dilation(0.0, (1.0, 1.0)).inverse(), None);
+    }
+}
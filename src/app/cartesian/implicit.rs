@@ -0,0 +1,122 @@
+/// Traces the zero set of `f` over the rectangle `min..max` with marching
+/// squares. The rectangle is split into `cells` coarse cells and every cell
+/// the curve may cross is subdivided `depth` more times before it is contoured.
+pub fn contour(
+    f: impl Fn(f64, f64) -> Option<f64>,
+    min: (f64, f64),
+    max: (f64, f64),
+    cells: (usize, usize),
+    depth: u32,
+) -> Vec<[(f64, f64); 2]> {
+    let (nx, ny) = cells;
+    let dx = (max.0 - min.0) / nx as f64;
+    let dy = (max.1 - min.1) / ny as f64;
+
+    let mut grid = vec![None; (nx + 1) * (ny + 1)];
+    for j in 0..=ny {
+        for i in 0..=nx {
+            grid[j * (nx + 1) + i] = f(min.0 + i as f64 * dx, min.1 + j as f64 * dy);
+        }
+    }
+
+    let mut segments = Vec::new();
+    for j in 0..ny {
+        for i in 0..nx {
+            let corners = [
+                grid[j * (nx + 1) + i],
+                grid[j * (nx + 1) + i + 1],
+                grid[(j + 1) * (nx + 1) + i + 1],
+                grid[(j + 1) * (nx + 1) + i],
+            ];
+            let origin = (min.0 + i as f64 * dx, min.1 + j as f64 * dy);
+            refine(&f, origin, (dx, dy), corners, depth, &mut segments);
+        }
+    }
+    segments
+}
+
+/// Corners are ordered bottom-left, bottom-right, top-right, top-left.
+fn refine(
+    f: &impl Fn(f64, f64) -> Option<f64>,
+    (x, y): (f64, f64),
+    (w, h): (f64, f64),
+    corners: [Option<f64>; 4],
+    depth: u32,
+    segments: &mut Vec<[(f64, f64); 2]>,
+) {
+    let Some(values) = corners.into_iter().collect::<Option<Vec<_>>>() else {
+        return;
+    };
+    let center = f(x + w / 2.0, y + h / 2.0);
+    let Some(center) = center.filter(|c| c.is_finite()) else {
+        return;
+    };
+
+    let signs = values.iter().map(|v| *v >= 0.0);
+    let crosses = signs.clone().any(|s| s != (center >= 0.0));
+    if !crosses {
+        return;
+    }
+
+    if depth > 0 {
+        let (hw, hh) = (w / 2.0, h / 2.0);
+        let mid = |dx: f64, dy: f64| f(x + dx, y + dy);
+        let bottom = mid(hw, 0.0);
+        let right = mid(w, hh);
+        let top = mid(hw, h);
+        let left = mid(0.0, hh);
+        let c = Some(center);
+        let quadrants = [
+            ((x, y), [corners[0], bottom, c, left]),
+            ((x + hw, y), [bottom, corners[1], right, c]),
+            ((x + hw, y + hh), [c, right, corners[2], top]),
+            ((x, y + hh), [left, c, top, corners[3]]),
+        ];
+        for (origin, corners) in quadrants {
+            refine(f, origin, (hw, hh), corners, depth - 1, segments);
+        }
+        return;
+    }
+
+    // A pole looks like a sign change too, but the value grows towards it
+    // instead of shrinking.
+    if values.iter().all(|v| center.abs() > v.abs()) {
+        return;
+    }
+
+    let points = [(x, y), (x + w, y), (x + w, y + h), (x, y + h)];
+    let crossing = |a: usize, b: usize| {
+        let (va, vb) = (values[a], values[b]);
+        if (va >= 0.0) == (vb >= 0.0) {
+            return None;
+        }
+        let t = va / (va - vb);
+        Some((
+            points[a].0 + t * (points[b].0 - points[a].0),
+            points[a].1 + t * (points[b].1 - points[a].1),
+        ))
+    };
+    let edges = [
+        crossing(0, 1),
+        crossing(1, 2),
+        crossing(3, 2),
+        crossing(0, 3),
+    ];
+
+    let found: Vec<_> = edges.iter().flatten().copied().collect();
+    match found.len() {
+        2 => segments.push([found[0], found[1]]),
+        4 => {
+            // Saddle: cut off the corners whose sign differs from the center.
+            let adjacent = [(0, 3), (0, 1), (1, 2), (2, 3)];
+            for (corner, (a, b)) in adjacent.into_iter().enumerate() {
+                if (values[corner] >= 0.0) != (center >= 0.0) {
+                    if let (Some(a), Some(b)) = (edges[a], edges[b]) {
+                        segments.push([a, b]);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,79 @@
+/// Integral of `f` over `a..b` by adaptive Simpson quadrature. `None` where
+/// `f` is undefined somewhere on the way or the integral diverges.
+pub fn integrate(f: impl Fn(f64) -> Option<f64>, a: f64, b: f64) -> Option<f64> {
+    let m = (a + b) / 2.0;
+    let (fa, fm, fb) = (f(a)?, f(m)?, f(b)?);
+    let whole = simpson(a, b, fa, fm, fb);
+    let mut budget = 20_000;
+    adaptive(&f, (a, b), (fa, fm, fb), whole, 1e-9, &mut budget, 0)
+        .filter(|value| value.is_finite())
+}
+
+/// Halvings past which an interval is taken as it is, well beyond what an
+/// `f64` interval can be split into.
+const MAX_DEPTH: u32 = 64;
+
+fn simpson(a: f64, b: f64, fa: f64, fm: f64, fb: f64) -> f64 {
+    (b - a) / 6.0 * (fa + 4.0 * fm + fb)
+}
+
+/// Refines `whole`, the Simpson estimate over `a..b`, until it is within
+/// `tolerance`, `budget` evaluations of `f` have been spent or the interval
+/// has been halved `MAX_DEPTH` times.
+fn adaptive(
+    f: &impl Fn(f64) -> Option<f64>,
+    (a, b): (f64, f64),
+    (fa, fm, fb): (f64, f64, f64),
+    whole: f64,
+    tolerance: f64,
+    budget: &mut u32,
+    depth: u32,
+) -> Option<f64> {
+    let m = (a + b) / 2.0;
+    let (flm, frm) = (f((a + m) / 2.0)?, f((m + b) / 2.0)?);
+    *budget = budget.saturating_sub(2);
+    let left = simpson(a, m, fa, flm, fm);
+    let right = simpson(m, b, fm, frm, fb);
+    let delta = left + right - whole;
+    if *budget == 0 || depth == MAX_DEPTH || delta.abs() <= 15.0 * tolerance || m == a || m == b {
+        // Richardson extrapolation of the two estimates.
+        return Some(left + right + delta / 15.0);
+    }
+    Some(
+        adaptive(
+            f,
+            (a, m),
+            (fa, flm, fm),
+            left,
+            tolerance / 2.0,
+            budget,
+            depth + 1,
+        )? + adaptive(
+            f,
+            (m, b),
+            (fm, frm, fb),
+            right,
+            tolerance / 2.0,
+            budget,
+            depth + 1,
+        )?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integrates_smooth_functions() {
+        let value = integrate(|x| Some(x.sin()), 0.0, std::f64::consts::PI).unwrap();
+        assert!((value - 2.0).abs() < 1e-9);
+        let value = integrate(|x| Some(x * x), -1.0, 2.0).unwrap();
+        assert!((value - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn infinite_bounds_do_not_overflow() {
+        assert_eq!(integrate(Some, f64::NEG_INFINITY, f64::INFINITY), None);
+    }
+}
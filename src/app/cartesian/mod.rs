@@ -0,0 +1,1715 @@
+mod cache;
+mod complex;
+mod derivative;
+mod domain;
+mod editor;
+mod geometry;
+mod implicit;
+mod integral;
+mod ode;
+mod ops;
+mod region;
+mod rewrite;
+mod roots;
+mod sampling;
+mod slider;
+mod style;
+mod symbols;
+
+use std::collections::{HashMap, HashSet};
+
+use domain::DomainColoring;
+use eframe::egui::{self, Color32, Pos2};
+use geometry::{Command, Shape};
+use slider::Slider;
+use style::{LineStyle, Style};
+use symbols::{Definition, SymbolError, SymbolTable, Value};
+
+struct Item {
+    input: String,
+    color: Color32,
+    /// Whether the derivative of a function is drawn along with it.
+    show_derivative: bool,
+    style: Style,
+    /// Points the solution curves of a differential equation pass through.
+    seeds: Vec<(f64, f64)>,
+}
+
+impl Item {
+    fn new() -> Self {
+        Self {
+            input: String::new(),
+            color: Color32::WHITE,
+            show_derivative: false,
+            style: Style::default(),
+            seeds: Vec::new(),
+        }
+    }
+}
+
+/// A root, extremum, inflection point or intersection of plotted functions.
+struct Marker {
+    kind: &'static str,
+    position: (f64, f64),
+}
+
+pub struct Cartesian {
+    inputs: Vec<Item>,
+    symbols: SymbolTable,
+    sliders: HashMap<String, Slider>,
+    side_bar_open: bool,
+    zoom: f32,
+    pan: Pos2,
+    axis_color: Color32,
+    grid_color: Color32,
+    polar_grid: bool,
+    show_markers: bool,
+    snap_to_grid: bool,
+    /// Whether complex functions are drawn as domain colorings.
+    complex_mode: bool,
+    contours: bool,
+    domain: DomainColoring,
+    /// World position of the marker whose label is pinned.
+    selected_marker: Option<(f64, f64)>,
+    pub switch: bool,
+}
+
+impl Cartesian {
+    pub fn update(&mut self, ctx: &egui::Context) {
+        self.update_symbols();
+        self.animate(ctx);
+
+        egui::TopBottomPanel::top("controls").show(ctx, |ui| {
+            ui.group(|ui| {
+                ui.set_height(45.0);
+                ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                    if !self.side_bar_open {
+                        if ui
+                            .add_sized([100.0, 10.0], egui::Button::new("Open"))
+                            .clicked()
+                        {
+                            self.side_bar_open = true;
+                        }
+                        ui.separator();
+                    }
+
+                    ui.vertical(|ui| {
+                        ui.label("Axis color:");
+                        ui.color_edit_button_srgba(&mut self.axis_color);
+                    });
+
+                    ui.separator();
+
+                    ui.vertical(|ui| {
+                        ui.label("Grid color:");
+                        ui.color_edit_button_srgba(&mut self.grid_color);
+                    });
+
+                    ui.separator();
+
+                    ui.checkbox(&mut self.polar_grid, "Polar grid")
+                        .on_hover_text("Draw concentric circles and angle rays");
+
+                    ui.checkbox(&mut self.show_markers, "Key points")
+                        .on_hover_text("Mark roots, extrema, inflection points and intersections");
+
+                    ui.checkbox(&mut self.snap_to_grid, "Snap to grid")
+                        .on_hover_text("Move dragged points to the nearest grid intersection");
+
+                    ui.separator();
+
+                    ui.checkbox(&mut self.complex_mode, "Complex")
+                        .on_hover_text("Color the plane by the values of a function f(z)");
+                    if self.complex_mode {
+                        ui.checkbox(&mut self.contours, "Contours").on_hover_text(
+                            "Outline where the modulus doubles and the argument turns by 30°",
+                        );
+                    }
+
+                    ui.separator();
+
+                    if ui
+                        .add_sized([100.0, 10.0], egui::Button::new("Reset"))
+                        .on_hover_text("Reset the view")
+                        .clicked()
+                    {
+                        self.zoom = 1.0;
+                        self.pan = Pos2::ZERO;
+                    }
+
+                    if ui
+                        .add_sized([100.0, 10.0], egui::Button::new("Bezier"))
+                        .on_hover_text("Switch to Bezier curve")
+                        .clicked()
+                    {
+                        self.switch = true;
+                    }
+                });
+            });
+        });
+
+        if self.side_bar_open {
+            egui::SidePanel::left("functions").show(ctx, |ui| {
+                ui.group(|ui| {
+                    ui.set_width(160.0);
+                    ui.add_sized([100.0, 10.0], egui::Button::new("Close"))
+                        .on_hover_text("Close the side bar")
+                        .clicked()
+                        .then(|| self.side_bar_open = false);
+
+                    ui.separator();
+
+                    ui.label("Items:");
+
+                    let mut to_remove = None;
+                    let mut to_add = None;
+                    let names = self.symbols.names();
+                    for (i, item) in &mut self.inputs.iter_mut().enumerate() {
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            ui.set_width(150.0);
+                            ui.color_edit_button_srgba(&mut item.color);
+                            editor::show(ui, i, &mut item.input, &names, self.symbols.get(i).err());
+                            if let Err(error) = self.symbols.get(i) {
+                                if !item.input.trim().is_empty() {
+                                    let kind = self.symbols.kind(i);
+                                    ui.label(
+                                        egui::RichText::new("⚠").color(ui.visuals().error_fg_color),
+                                    )
+                                    .on_hover_ui(|ui| error_tooltip(ui, &item.input, kind, error));
+                                }
+                            }
+                            if ui
+                                .add_sized([20.0, 20.0], egui::Button::new("X"))
+                                .on_hover_text("Remove item")
+                                .clicked()
+                            {
+                                to_remove = Some(i);
+                            }
+                        });
+
+                        if let Some((name, mut value)) = self.symbols.literal(i) {
+                            let slider = self
+                                .sliders
+                                .entry(name.to_string())
+                                .or_insert_with(|| Slider::new(value));
+                            if slider.ui(ui, i, &mut value) {
+                                rewrite::rewrite_value(&mut item.input, value);
+                            }
+                        }
+
+                        if let Some((_, _, value)) = self.symbols.integral(i) {
+                            ui.label(format!("∫ = {:.4}", value));
+                        }
+
+                        if let (
+                            Ok(Definition::Construction { command, .. }),
+                            Some(&Value::Number(value)),
+                        ) = (self.symbols.get(i), self.symbols.line_value(i))
+                        {
+                            ui.label(format!("= {}", measurement_label(*command, value)));
+                        }
+
+                        match (self.symbols.get(i), self.symbols.line_value(i)) {
+                            (Ok(Definition::Vector { .. }), Some(&Value::Vector(x, y))) => {
+                                ui.label(format!("= <{:.4}, {:.4}>", x, y));
+                                ui.label(format!("Length: {:.4}", x.hypot(y)));
+                            }
+                            (
+                                Ok(Definition::Number { value, .. }),
+                                Some(&Value::Number(number)),
+                            ) if value.literal().is_none() => {
+                                ui.label(format!("= {:.4}", number));
+                            }
+                            _ => {}
+                        }
+
+                        if let Some((x, y, slope)) = self.symbols.tangent(i) {
+                            ui.label(format!("Slope: {:.4}", slope));
+                            ui.label(format!("Tangent: {}", line_equation((x, y), slope)));
+                            ui.label(format!("Normal: {}", line_equation((x, y), -1.0 / slope)));
+                        }
+
+                        if let Ok(Definition::Construction { command, .. }) = self.symbols.get(i) {
+                            let filled = *command == Command::Angle
+                                || matches!(
+                                    self.symbols.line_value(i),
+                                    Some(Value::Shape(
+                                        Shape::Circle { .. }
+                                            | Shape::Polygon(_)
+                                            | Shape::Ellipse { .. }
+                                    ))
+                                );
+                            item.style.ui(ui, i, filled);
+                        }
+
+                        if let Ok(Definition::Differential { .. }) = self.symbols.get(i) {
+                            ui.horizontal(|ui| {
+                                ui.label(format!("Solutions: {}", item.seeds.len()))
+                                    .on_hover_text(
+                                        "Click the canvas to draw the solution through a point",
+                                    );
+                                if ui
+                                    .button("Clear")
+                                    .on_hover_text("Remove every solution curve")
+                                    .clicked()
+                                {
+                                    item.seeds.clear();
+                                }
+                            });
+                        }
+
+                        if self.symbols.has_derivative(i) {
+                            ui.checkbox(&mut item.show_derivative, "Show derivative")
+                                .on_hover_text("Draw the derivative in a lighter shade");
+                        }
+
+                        let graph = match self.symbols.get(i) {
+                            Ok(Definition::UserFunction { name, params, .. })
+                                if params.len() == 1 =>
+                            {
+                                Some(name.as_str())
+                            }
+                            Ok(Definition::Function { .. }) => Some(
+                                item.input
+                                    .split_once('=')
+                                    .map_or(item.input.as_str(), |(_, body)| body)
+                                    .trim(),
+                            ),
+                            _ => None,
+                        };
+                        // A piecewise body can't be written into a `tangent` line.
+                        let tangent =
+                            graph
+                                .map(|graph| format!("tangent({}, 1)", graph))
+                                .filter(|line| {
+                                    matches!(
+                                        symbols::parse_definition(line),
+                                        Ok(Definition::Tangent { .. })
+                                    )
+                                });
+                        if let Some(tangent) = tangent {
+                            if ui
+                                .button("Tangent")
+                                .on_hover_text(
+                                    "Add a point on this graph with its tangent and normal",
+                                )
+                                .clicked()
+                            {
+                                to_add = Some(tangent);
+                            }
+                        }
+                    }
+                    ui.separator();
+                    ui.add_sized([100.0, 10.0], egui::Button::new("Add"))
+                        .on_hover_text("Add a new item")
+                        .clicked()
+                        .then(|| self.inputs.push(Item::new()));
+                    if let Some(input) = to_add {
+                        self.inputs.push(Item {
+                            input,
+                            ..Item::new()
+                        });
+                    }
+                    if let Some(i) = to_remove {
+                        self.inputs.remove(i);
+                    }
+
+                    /*
+                    let mut points_string = String::new();
+                    for (name, x, y) in &self.environment.points {
+                        points_string.push_str(&format!("{}=({}, {})\n", name, x, y));
+                    }
+
+                    ui.separator();
+                    ui.label("Points:");
+                    if ui.text_edit_multiline(&mut points_string).changed() {
+                        let mut new_points = Vec::new();
+
+                        for line in points_string.lines() {
+                            if let Some((name, coords)) = line.split_once('=') {
+                                if let Some((x_str, y_str)) = coords
+                                    .trim()
+                                    .strip_prefix('(')
+                                    .and_then(|s| s.strip_suffix(')'))
+                                    .and_then(|s| s.split_once(','))
+                                {
+                                    if let (Ok(x), Ok(y)) =
+                                        (x_str.trim().parse(), y_str.trim().parse())
+                                    {
+                                        new_points.push((name.trim().to_string(), x, y));
+                                    }
+                                }
+                            }
+                        }
+
+                        self.environment.points = new_points;
+                    }
+
+                    if ui
+                        .add_sized([100.0, 10.0], egui::Button::new("Add point"))
+                        .on_hover_text("Add a new point")
+                        .clicked()
+                    {
+                        let new_name = format!("P{}", self.environment.points.len() + 1);
+                        self.environment.add_point(&new_name, 0.0, 0.0);
+                    }
+                    */
+                });
+            });
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let rect = ui.min_rect();
+            let response = ui.interact(rect, ui.id(), egui::Sense::click_and_drag());
+            self.pan += response.drag_delta();
+            if let Some(pointer) = response
+                .interact_pointer_pos()
+                .filter(|_| response.clicked())
+            {
+                self.seed(self.to_world(rect, pointer));
+            }
+            let mouse_pos_before_zoom = ui.input(|i| i.pointer.hover_pos()).unwrap_or_default();
+
+            ui.input(|i| {
+                let zoom_factor = 1.0 + i.smooth_scroll_delta.y * 0.01;
+                let new_zoom = (self.zoom * zoom_factor).clamp(0.1, 10.0);
+
+                let zoom_adjustment = mouse_pos_before_zoom - (rect.center() + self.pan.to_vec2());
+                self.pan += zoom_adjustment * (1.0 - zoom_factor);
+                self.zoom = new_zoom;
+            });
+
+            self.update_symbols();
+
+            if self.complex_mode {
+                self.draw_domain_coloring(ui, rect);
+            }
+            self.draw_grid(ui, rect);
+            for i in 0..self.inputs.len() {
+                match self.symbols.get(i) {
+                    Ok(Definition::Point { name, .. }) => {
+                        if let Some(Value::Point(x, y)) = self.symbols.value(name) {
+                            self.draw_point(ui, rect, name, (x, y), self.inputs[i].color);
+                        }
+                    }
+                    Ok(Definition::Function { .. }) => self.draw_function(ui, rect, i),
+                    // In complex mode a function of `z` is its domain coloring.
+                    Ok(Definition::UserFunction { params, .. })
+                        if params.len() == 1
+                            && !(self.complex_mode && self.symbols.complex(i).is_some()) =>
+                    {
+                        self.draw_function(ui, rect, i)
+                    }
+                    Ok(Definition::Implicit { .. }) => self.draw_implicit(ui, rect, i),
+                    Ok(Definition::Parametric { .. }) => self.draw_parametric(ui, rect, i),
+                    Ok(Definition::Polar { .. }) => self.draw_polar(ui, rect, i),
+                    Ok(Definition::Inequality { .. }) => self.draw_inequality(ui, rect, i),
+                    Ok(Definition::Integral { .. }) => self.draw_integral(ui, rect, i),
+                    Ok(Definition::Tangent { .. }) => self.draw_tangent(ui, rect, i),
+                    Ok(Definition::Construction { .. }) => self.draw_shape(ui, rect, i),
+                    Ok(Definition::Differential { .. }) => self.draw_differential(ui, rect, i),
+                    Ok(Definition::Vector { .. }) => self.draw_vector(ui, rect, i),
+                    Ok(Definition::VectorField { .. }) => self.draw_vector_field(ui, rect, i),
+                    _ => {}
+                }
+            }
+
+            if self.show_markers {
+                self.draw_markers(ui, rect);
+            }
+            self.drag_points(ui, rect);
+            self.drag_seeds(ui, rect);
+        });
+    }
+
+    /// Resolves the inputs again, dropping the sliders of variables that
+    /// were renamed or removed.
+    fn update_symbols(&mut self) {
+        if !self
+            .symbols
+            .update(self.inputs.iter().map(|item| item.input.as_str()))
+        {
+            return;
+        }
+        let variables: HashSet<&str> = (0..self.inputs.len())
+            .filter_map(|i| self.symbols.literal(i))
+            .map(|(name, _)| name)
+            .collect();
+        self.sliders
+            .retain(|name, _| variables.contains(name.as_str()));
+    }
+
+    /// Advances every playing slider and keeps repainting while any is.
+    fn animate(&mut self, ctx: &egui::Context) {
+        let dt = ctx.input(|i| i.stable_dt) as f64;
+        let mut playing = false;
+        for (i, item) in self.inputs.iter_mut().enumerate() {
+            let Some((name, value)) = self.symbols.literal(i) else {
+                continue;
+            };
+            let Some(slider) = self.sliders.get_mut(name).filter(|s| s.playing) else {
+                continue;
+            };
+            rewrite::rewrite_value(&mut item.input, slider.advance(value, dt));
+            playing = true;
+        }
+
+        if playing {
+            ctx.request_repaint();
+        }
+    }
+
+    /// Draws the domain coloring of the last complex function, or real
+    /// function of `z`, under everything else.
+    fn draw_domain_coloring(&mut self, ui: &mut egui::Ui, rect: egui::Rect) {
+        let Some(function) = (0..self.inputs.len())
+            .rev()
+            .find_map(|i| self.symbols.complex(i))
+        else {
+            return;
+        };
+        let view = (
+            self.to_world(rect, rect.left_bottom()),
+            self.to_world(rect, rect.right_top()),
+        );
+        let size = [rect.width() as usize, rect.height() as usize];
+        if size.contains(&0) {
+            return;
+        }
+        let Some((texture, (min, max))) =
+            self.domain
+                .update(ui.ctx(), function, view, size, self.contours)
+        else {
+            return;
+        };
+        let image = egui::Rect::from_two_pos(
+            self.to_screen(rect, (min.0, max.1)),
+            self.to_screen(rect, (max.0, min.1)),
+        );
+        ui.painter().image(
+            texture,
+            image,
+            egui::Rect::from_min_max(Pos2::ZERO, egui::pos2(1.0, 1.0)),
+            Color32::WHITE,
+        );
+    }
+
+    fn draw_grid(&self, ui: &mut egui::Ui, rect: egui::Rect) {
+        let grid_spacing = 40.0 * self.zoom;
+        let center: Pos2 = rect.center() + self.pan.to_vec2();
+
+        if self.polar_grid {
+            self.draw_polar_grid(ui, rect, center, grid_spacing);
+        } else {
+            self.draw_square_grid(ui, rect, center, grid_spacing);
+        }
+
+        ui.painter().line_segment(
+            [
+                egui::pos2(rect.left(), center.y),
+                egui::pos2(rect.right(), center.y),
+            ],
+            egui::Stroke::new(2.0, self.axis_color),
+        );
+        ui.painter().line_segment(
+            [
+                egui::pos2(center.x, rect.top()),
+                egui::pos2(center.x, rect.bottom()),
+            ],
+            egui::Stroke::new(2.0, self.axis_color),
+        );
+    }
+
+    fn draw_polar_grid(
+        &self,
+        ui: &mut egui::Ui,
+        rect: egui::Rect,
+        center: Pos2,
+        grid_spacing: f32,
+    ) {
+        let stroke = egui::Stroke::new(1.0, self.grid_color);
+        let farthest = [
+            rect.left_top(),
+            rect.right_top(),
+            rect.left_bottom(),
+            rect.right_bottom(),
+        ]
+        .iter()
+        .map(|corner| corner.distance(center))
+        .fold(0.0, f32::max);
+        let nearest = if rect.contains(center) {
+            0.0
+        } else {
+            rect.distance_to_pos(center)
+        };
+
+        let mut radius = (nearest / grid_spacing).floor().max(1.0) * grid_spacing;
+        while radius <= farthest {
+            ui.painter().circle_stroke(center, radius, stroke);
+            radius += grid_spacing;
+        }
+
+        let rays = 24;
+        for k in 0..rays {
+            let angle = std::f32::consts::TAU * k as f32 / rays as f32;
+            let end = center + egui::vec2(angle.cos(), -angle.sin()) * farthest;
+            ui.painter().line_segment([center, end], stroke);
+        }
+    }
+
+    fn draw_square_grid(
+        &self,
+        ui: &mut egui::Ui,
+        rect: egui::Rect,
+        center: Pos2,
+        grid_spacing: f32,
+    ) {
+        let mut x = center.x % grid_spacing;
+        while x < rect.right() {
+            ui.painter().line_segment(
+                [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+                egui::Stroke::new(1.0, self.grid_color),
+            );
+            x += grid_spacing;
+        }
+
+        let mut y = center.y % grid_spacing;
+        while y < rect.bottom() {
+            ui.painter().line_segment(
+                [egui::pos2(rect.left(), y), egui::pos2(rect.right(), y)],
+                egui::Stroke::new(1.0, self.grid_color),
+            );
+            y += grid_spacing;
+        }
+    }
+
+    fn draw_function(&self, ui: &mut egui::Ui, rect: egui::Rect, i: usize) {
+        let color = self.inputs[i].color;
+        self.draw_graph(ui, rect, |x| self.evaluate_expression(i, x), color);
+        self.draw_endpoints(ui, rect, i);
+        if self.inputs[i].show_derivative {
+            self.draw_graph(
+                ui,
+                rect,
+                |x| self.symbols.evaluate_derivative(i, x),
+                color.lerp_to_gamma(Color32::WHITE, 0.5),
+            );
+        }
+    }
+
+    /// Marks where the pieces of a piecewise function end, with a filled
+    /// circle where the end belongs to the piece and an open one where not.
+    fn draw_endpoints(&self, ui: &mut egui::Ui, rect: egui::Rect, i: usize) {
+        let color = self.inputs[i].color;
+        let (left, _) = self.to_world(rect, rect.left_top());
+        let (right, _) = self.to_world(rect, rect.right_bottom());
+        let pixel = (right - left) / rect.width() as f64;
+
+        let mut xs = Vec::new();
+        for (difference, _) in self.symbols.boundaries(i) {
+            let d = |x: f64| difference.eval(&[x]);
+            xs.extend(
+                roots::find_roots(d, left, right, rect.width() as usize)
+                    .into_iter()
+                    .map(|(x, _)| x),
+            );
+        }
+        xs.sort_by(f64::total_cmp);
+        xs.dedup_by(|a, b| (*a - *b).abs() < pixel);
+
+        let f = |x| self.evaluate_expression(i, x);
+        let mut ends = Vec::new();
+        for x in xs {
+            ends.extend(ends_at(f, x, pixel));
+        }
+
+        // Where two pieces meet, the closed end is drawn over the open one.
+        ends.sort_by_key(|&(_, closed)| closed);
+        for (end, closed) in ends {
+            let fill = if closed {
+                color
+            } else {
+                ui.visuals().panel_fill
+            };
+            ui.painter().circle(
+                self.to_screen(rect, end),
+                3.5,
+                fill,
+                egui::Stroke::new(1.0, color),
+            );
+        }
+    }
+
+    /// Draws the graph of `f` sampled adaptively, broken at poles and jumps,
+    /// with an open circle at every hole.
+    fn draw_graph(
+        &self,
+        ui: &mut egui::Ui,
+        rect: egui::Rect,
+        f: impl Fn(f64) -> Option<f64>,
+        color: Color32,
+    ) {
+        let (left, top) = self.to_world(rect, rect.left_top());
+        let (right, bottom) = self.to_world(rect, rect.right_bottom());
+        let pixel = (right - left) / rect.width() as f64;
+        let graph = sampling::sample(f, left, right, pixel);
+
+        // Far off-screen points only set the direction of the line leaving
+        // the screen, so they are pulled in to keep screen coordinates small.
+        let margin = 1e4 * (top - bottom);
+        let clamp = |(x, y): (f64, f64)| (x, y.clamp(bottom - margin, top + margin));
+        for path in graph.paths {
+            let points = path.into_iter().map(|point| Some(clamp(point)));
+            self.draw_path(ui, rect, points, color);
+        }
+
+        for hole in graph.holes {
+            ui.painter().circle(
+                self.to_screen(rect, hole),
+                3.5,
+                ui.visuals().panel_fill,
+                egui::Stroke::new(1.0, color),
+            );
+        }
+    }
+
+    /// Whether line `i` is plotted as the graph of a function of `x`.
+    fn is_graph(&self, i: usize) -> bool {
+        match self.symbols.get(i) {
+            Ok(Definition::Function { .. }) => true,
+            Ok(Definition::UserFunction { params, .. }) => params.len() == 1,
+            _ => false,
+        }
+    }
+
+    /// Finds the key points of every graph in the visible range.
+    fn markers(&self, rect: egui::Rect) -> Vec<Marker> {
+        let (from, _) = self.to_world(rect, rect.left_top());
+        let (to, _) = self.to_world(rect, rect.right_top());
+        let samples = (rect.width() / 4.0).ceil().max(1.0) as usize;
+        let graphs: Vec<usize> = (0..self.inputs.len())
+            .filter(|&i| self.is_graph(i))
+            .collect();
+
+        let mut markers = Vec::new();
+        for &i in &graphs {
+            let f = |x| self.evaluate_expression(i, x);
+            let slope = |x| self.symbols.evaluate_derivative(i, x);
+            let h = 1e-4;
+            let curvature = |x| Some((slope(x + h)? - slope(x - h)?) / (2.0 * h));
+
+            for (x, _) in roots::find_roots(f, from, to, samples) {
+                markers.push(Marker {
+                    kind: "Root",
+                    position: (x, 0.0),
+                });
+            }
+            for (x, rising) in roots::find_roots(slope, from, to, samples) {
+                if let Some(y) = f(x) {
+                    markers.push(Marker {
+                        kind: if rising { "Minimum" } else { "Maximum" },
+                        position: (x, y),
+                    });
+                }
+            }
+            for (x, _) in roots::find_roots(curvature, from, to, samples) {
+                if let Some(y) = f(x) {
+                    markers.push(Marker {
+                        kind: "Inflection point",
+                        position: (x, y),
+                    });
+                }
+            }
+        }
+
+        for (k, &i) in graphs.iter().enumerate() {
+            for &j in &graphs[k + 1..] {
+                let difference =
+                    |x| Some(self.evaluate_expression(i, x)? - self.evaluate_expression(j, x)?);
+                for (x, _) in roots::find_roots(difference, from, to, samples) {
+                    if let Some(y) = self.evaluate_expression(i, x) {
+                        markers.push(Marker {
+                            kind: "Intersection",
+                            position: (x, y),
+                        });
+                    }
+                }
+            }
+        }
+        markers
+    }
+
+    /// Draws the key points as gray markers. Hovering one shows its
+    /// coordinates and clicking it pins them next to it.
+    fn draw_markers(&mut self, ui: &mut egui::Ui, rect: egui::Rect) {
+        let color = Color32::GRAY;
+        for (k, marker) in self.markers(rect).into_iter().enumerate() {
+            let pos = self.to_screen(rect, marker.position);
+            if !rect.contains(pos) {
+                continue;
+            }
+            let (x, y) = marker.position;
+            let text = format!("{} ({:.4}, {:.4})", marker.kind, x, y);
+
+            ui.painter()
+                .circle(pos, 4.0, color, egui::Stroke::new(1.0, Color32::BLACK));
+            let response = ui
+                .interact(
+                    egui::Rect::from_center_size(pos, egui::vec2(12.0, 12.0)),
+                    ui.id().with(("marker", k)),
+                    egui::Sense::click(),
+                )
+                .on_hover_text(&text);
+
+            let selected = self
+                .selected_marker
+                .is_some_and(|selected| self.to_screen(rect, selected).distance(pos) < 2.0);
+            if response.clicked() {
+                self.selected_marker = (!selected).then_some(marker.position);
+            }
+            if selected {
+                ui.painter().text(
+                    pos + egui::vec2(8.0, -8.0),
+                    egui::Align2::LEFT_BOTTOM,
+                    text,
+                    egui::FontId::default(),
+                    color,
+                );
+            }
+        }
+    }
+
+    fn draw_implicit(&self, ui: &mut egui::Ui, rect: egui::Rect, i: usize) {
+        let cell_size = 16.0;
+        let min = self.to_world(rect, rect.left_bottom());
+        let max = self.to_world(rect, rect.right_top());
+        let cells = (
+            (rect.width() / cell_size).ceil().max(1.0) as usize,
+            (rect.height() / cell_size).ceil().max(1.0) as usize,
+        );
+
+        let segments =
+            implicit::contour(|x, y| self.symbols.evaluate(i, &[x, y]), min, max, cells, 2);
+        let stroke = egui::Stroke::new(1.0, self.inputs[i].color);
+        for [a, b] in segments {
+            ui.painter()
+                .line_segment([self.to_screen(rect, a), self.to_screen(rect, b)], stroke);
+        }
+    }
+
+    fn draw_inequality(&self, ui: &mut egui::Ui, rect: egui::Rect, i: usize) {
+        let (Ok(Definition::Inequality { conditions }), Some(compiled)) =
+            (self.symbols.get(i), self.symbols.compiled(i))
+        else {
+            return;
+        };
+        let color = self.inputs[i].color;
+        let holds = |k: usize, x: f64, y: f64| {
+            compiled[k]
+                .eval(&[x, y])
+                .is_some_and(|value| conditions[k].1.holds(value))
+        };
+
+        let cell_size = 8.0;
+        let min = self.to_world(rect, rect.left_bottom());
+        let max = self.to_world(rect, rect.right_top());
+        let cells = (
+            (rect.width() / cell_size).ceil().max(1.0) as usize,
+            (rect.height() / cell_size).ceil().max(1.0) as usize,
+        );
+
+        let inside = |x, y| (0..conditions.len()).all(|k| holds(k, x, y));
+        let mut mesh = egui::Mesh::default();
+        for [a, b] in region::fill(inside, min, max, cells, 2) {
+            let area = egui::Rect::from_two_pos(self.to_screen(rect, a), self.to_screen(rect, b));
+            mesh.add_colored_rect(area, color.gamma_multiply(0.25));
+        }
+        ui.painter().add(egui::Shape::mesh(mesh));
+
+        let stroke = egui::Stroke::new(1.0, color);
+        for (k, (_, comparison)) in conditions.iter().enumerate() {
+            let segments = implicit::contour(|x, y| compiled[k].eval(&[x, y]), min, max, cells, 2);
+            for [a, b] in segments {
+                let mid = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+                if (0..conditions.len()).any(|other| other != k && !holds(other, mid.0, mid.1)) {
+                    continue;
+                }
+                let (a, b) = (self.to_screen(rect, a), self.to_screen(rect, b));
+                if comparison.is_strict() {
+                    draw_dashed_segment(ui, a, b, stroke, 5.0);
+                } else {
+                    ui.painter().line_segment([a, b], stroke);
+                }
+            }
+        }
+    }
+
+    /// Shades the signed area between the integrand and the x axis, above the
+    /// axis in a stronger tone than below it.
+    fn draw_integral(&self, ui: &mut egui::Ui, rect: egui::Rect, i: usize) {
+        let (Some([integrand, ..]), Some((a, b, value))) =
+            (self.symbols.compiled(i), self.symbols.integral(i))
+        else {
+            return;
+        };
+        let color = self.inputs[i].color;
+        let tones = (color.gamma_multiply(0.4), color.gamma_multiply(0.15));
+        let f = |x: f64| integrand.eval(&[x]).filter(|y| y.is_finite());
+
+        let (left, _) = self.to_world(rect, rect.left_top());
+        let (right, _) = self.to_world(rect, rect.right_top());
+        let (from, to) = (a.min(b).max(left), a.max(b).min(right));
+        if from < to {
+            let steps = ((to - from) * (self.zoom * 40.0) as f64).ceil().max(1.0) as usize;
+            let points: Vec<_> = (0..=steps)
+                .map(|step| {
+                    let x = from + (to - from) * step as f64 / steps as f64;
+                    f(x).map(|y| (x, y))
+                })
+                .collect();
+
+            let mut mesh = egui::Mesh::default();
+            for pair in points.windows(2) {
+                if let [Some(p0), Some(p1)] = *pair {
+                    self.shade_column(&mut mesh, rect, p0, p1, tones);
+                }
+            }
+            ui.painter().add(egui::Shape::mesh(mesh));
+            self.draw_path(ui, rect, points.into_iter(), color);
+        }
+
+        let stroke = egui::Stroke::new(1.0, color);
+        for x in [a, b] {
+            if let Some(y) = f(x) {
+                let (base, top) = (self.to_screen(rect, (x, 0.0)), self.to_screen(rect, (x, y)));
+                draw_dashed_segment(ui, base, top, stroke, 5.0);
+            }
+        }
+
+        let mid = (a + b) / 2.0;
+        let label = self.to_screen(rect, (mid, f(mid).unwrap_or_default() / 2.0));
+        ui.painter().text(
+            label,
+            egui::Align2::CENTER_CENTER,
+            format!("∫ = {:.4}", value),
+            egui::FontId::default(),
+            color,
+        );
+    }
+
+    /// Adds the area between the x axis and the segment `p0..p1` to `mesh`,
+    /// split where the segment crosses the axis.
+    fn shade_column(
+        &self,
+        mesh: &mut egui::Mesh,
+        rect: egui::Rect,
+        (x0, y0): (f64, f64),
+        (x1, y1): (f64, f64),
+        (positive, negative): (Color32, Color32),
+    ) {
+        let tone = |y: f64| if y >= 0.0 { positive } else { negative };
+        let mut quad = |corners: [(f64, f64); 4], color: Color32| {
+            let first = mesh.vertices.len() as u32;
+            for corner in corners {
+                mesh.colored_vertex(self.to_screen(rect, corner), color);
+            }
+            mesh.add_triangle(first, first + 1, first + 2);
+            mesh.add_triangle(first, first + 2, first + 3);
+        };
+
+        if (y0 >= 0.0) == (y1 >= 0.0) {
+            quad([(x0, 0.0), (x0, y0), (x1, y1), (x1, 0.0)], tone(y0));
+        } else {
+            let xm = x0 + (x1 - x0) * y0 / (y0 - y1);
+            quad([(x0, 0.0), (x0, y0), (xm, 0.0), (xm, 0.0)], tone(y0));
+            quad([(xm, 0.0), (xm, 0.0), (x1, y1), (x1, 0.0)], tone(y1));
+        }
+    }
+
+    /// Draws the tangent and, dashed, the normal through the glider, and lets
+    /// the glider be dragged along the graph unless its x coordinate is an
+    /// expression.
+    fn draw_tangent(&mut self, ui: &mut egui::Ui, rect: egui::Rect, i: usize) {
+        let Some((x, y, slope)) = self.symbols.tangent(i) else {
+            return;
+        };
+        let color = self.inputs[i].color;
+        let stroke = egui::Stroke::new(1.0, color);
+        let (left, top) = self.to_world(rect, rect.left_top());
+        let (right, bottom) = self.to_world(rect, rect.right_bottom());
+        let reach = (right - left).hypot(top - bottom);
+
+        let (dx, dy) = if slope.is_finite() {
+            let length = slope.hypot(1.0);
+            (1.0 / length, slope / length)
+        } else {
+            (0.0, 1.0)
+        };
+        let line = |(dx, dy): (f64, f64)| {
+            [
+                self.to_screen(rect, (x - dx * reach, y - dy * reach)),
+                self.to_screen(rect, (x + dx * reach, y + dy * reach)),
+            ]
+        };
+        ui.painter().line_segment(line((dx, dy)), stroke);
+        let [a, b] = line((-dy, dx));
+        draw_dashed_segment(ui, a, b, stroke, 5.0);
+
+        let pos = self.to_screen(rect, (x, y));
+        ui.painter()
+            .circle(pos, 5.0, color, egui::Stroke::new(1.0, color));
+
+        let source = self.symbols.glider_source(i);
+        let response = ui.interact(
+            egui::Rect::from_center_size(pos, egui::vec2(14.0, 14.0)),
+            ui.id().with(("glider", i)),
+            egui::Sense::drag(),
+        );
+        let Some(j) = source else {
+            response.on_hover_text("The x coordinate is an expression");
+            return;
+        };
+        let response = response.on_hover_cursor(egui::CursorIcon::Grab);
+        if let Some(pointer) = response
+            .interact_pointer_pos()
+            .filter(|_| response.dragged())
+        {
+            let (x, _) = self.to_world(rect, pointer);
+            if j == i {
+                rewrite::rewrite_last_argument(&mut self.inputs[j].input, x);
+            } else {
+                rewrite::rewrite_value(&mut self.inputs[j].input, x);
+            }
+        }
+    }
+
+    fn draw_shape(&self, ui: &mut egui::Ui, rect: egui::Rect, i: usize) {
+        let Item { color, style, .. } = &self.inputs[i];
+        let shape = match self.symbols.line_value(i) {
+            Some(Value::Shape(shape)) => shape,
+            // Midpoints and single intersections.
+            Some(&Value::Point(x, y)) => {
+                let name = self.symbols.get(i).ok().and_then(|d| d.name());
+                self.draw_point(ui, rect, name.unwrap_or_default(), (x, y), *color);
+                return;
+            }
+            Some(&Value::Number(value)) => {
+                self.draw_measurement(ui, rect, i, value);
+                return;
+            }
+            _ => return,
+        };
+        let stroke = style.stroke(*color);
+        let fill = color.gamma_multiply(style.fill);
+        let (left, top) = self.to_world(rect, rect.left_top());
+        let (right, bottom) = self.to_world(rect, rect.right_bottom());
+        let center = ((left + right) / 2.0, (top + bottom) / 2.0);
+        let reach = (right - left).hypot(top - bottom);
+
+        // Lines and rays are drawn far enough past the view to cover it.
+        let extend = |a: (f64, f64), b: (f64, f64), backwards: bool| {
+            let length = (b.0 - a.0).hypot(b.1 - a.1);
+            let (dx, dy) = ((b.0 - a.0) / length, (b.1 - a.1) / length);
+            let far = reach + (a.0 - center.0).hypot(a.1 - center.1);
+            let back = if backwards { far } else { 0.0 };
+            [
+                self.to_screen(rect, (a.0 - dx * back, a.1 - dy * back)),
+                self.to_screen(rect, (a.0 + dx * far, a.1 + dy * far)),
+            ]
+        };
+
+        let polygon = |ui: &mut egui::Ui, points: &[(f64, f64)]| {
+            let outline: Vec<Pos2> = points.iter().map(|&p| self.to_screen(rect, p)).collect();
+            let mut mesh = egui::Mesh::default();
+            for &pos in &outline {
+                mesh.colored_vertex(pos, fill);
+            }
+            for [a, b, c] in geometry::triangulate(points) {
+                mesh.add_triangle(a as u32, b as u32, c as u32);
+            }
+            ui.painter().add(mesh);
+            for k in 0..outline.len() {
+                let next = outline[(k + 1) % outline.len()];
+                draw_styled_segment(ui, outline[k], next, stroke, style.line);
+            }
+        };
+
+        match shape {
+            Shape::Segment(a, b) => {
+                let [a, b] = [*a, *b].map(|p| self.to_screen(rect, p));
+                draw_styled_segment(ui, a, b, stroke, style.line);
+            }
+            Shape::Line(a, b) => {
+                let [a, b] = extend(*a, *b, true);
+                draw_styled_segment(ui, a, b, stroke, style.line);
+            }
+            Shape::Ray(a, b) => {
+                let [a, b] = extend(*a, *b, false);
+                draw_styled_segment(ui, a, b, stroke, style.line);
+            }
+            Shape::Circle { center, radius } => {
+                let pos = self.to_screen(rect, *center);
+                let screen_radius = *radius as f32 * self.zoom * 40.0;
+                ui.painter().circle_filled(pos, screen_radius, fill);
+                if style.line == LineStyle::Solid {
+                    ui.painter().circle_stroke(pos, screen_radius, stroke);
+                } else {
+                    let steps = 128;
+                    let outline: Vec<Pos2> = (0..=steps)
+                        .map(|k| {
+                            let angle = std::f32::consts::TAU * k as f32 / steps as f32;
+                            pos + screen_radius * egui::vec2(angle.cos(), angle.sin())
+                        })
+                        .collect();
+                    for pair in outline.windows(2) {
+                        draw_styled_segment(ui, pair[0], pair[1], stroke, style.line);
+                    }
+                }
+            }
+            Shape::Points(points) => {
+                for &point in points {
+                    let pos = self.to_screen(rect, point);
+                    ui.painter().circle_filled(pos, 4.0, *color);
+                }
+            }
+            Shape::Lines(lines) => {
+                for &(a, b) in lines {
+                    let [a, b] = extend(a, b, true);
+                    draw_styled_segment(ui, a, b, stroke, style.line);
+                }
+            }
+            Shape::Polygon(points) => polygon(ui, points),
+            &Shape::Ellipse { center, axes } => {
+                let mut points = geometry::ellipse(center, axes, 128);
+                points.pop();
+                polygon(ui, &points);
+            }
+            Shape::Graph(map) => {
+                let f = self.symbols.graph_source(i);
+                let (Some(f), Some(inverse)) = (f, map.inverse()) else {
+                    return;
+                };
+                // The part of the graph that is mapped into view.
+                let corners = [
+                    rect.left_top(),
+                    rect.right_top(),
+                    rect.left_bottom(),
+                    rect.right_bottom(),
+                ]
+                .map(|pos| inverse.apply(self.to_world(rect, pos)).0);
+                let from = corners.into_iter().fold(f64::INFINITY, f64::min);
+                let to = corners.into_iter().fold(f64::NEG_INFINITY, f64::max);
+                let pixel = (right - left) / rect.width() as f64 / map.scale();
+                let graph = sampling::sample(|x| f.eval(&[x]), from, to, pixel);
+
+                let margin = 1e4 * (to - from);
+                for path in graph.paths {
+                    let path: Vec<Pos2> = path
+                        .into_iter()
+                        .map(|(x, y)| {
+                            self.to_screen(rect, map.apply((x, y.clamp(-margin, margin))))
+                        })
+                        .collect();
+                    for pair in path.windows(2) {
+                        draw_styled_segment(ui, pair[0], pair[1], stroke, style.line);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Labels a distance, area or perimeter next to what it measures, and
+    /// marks an angle with an arc.
+    fn draw_measurement(&self, ui: &mut egui::Ui, rect: egui::Rect, i: usize, value: f64) {
+        let (
+            Ok(Definition::Construction {
+                command, shapes, ..
+            }),
+            Some(args),
+        ) = (self.symbols.get(i), self.symbols.compiled(i))
+        else {
+            return;
+        };
+        let points: Vec<(f64, f64)> = args
+            .chunks_exact(2)
+            .filter_map(|p| p[0].eval(&[]).zip(p[1].eval(&[])))
+            .collect();
+        let Item { color, style, .. } = &self.inputs[i];
+        let name = self.symbols.get(i).ok().and_then(|d| d.name());
+        let label = format!(
+            "{} = {}",
+            name.unwrap_or(command.kind()),
+            measurement_label(*command, value)
+        );
+        let painter = ui.painter().clone();
+        let text = |pos: Pos2, align: egui::Align2| {
+            painter.text(pos, align, &label, egui::FontId::default(), *color);
+        };
+
+        match command {
+            Command::Distance => {
+                let [a, b] = [points[0], points[1]].map(|p| self.to_screen(rect, p));
+                text(
+                    a.lerp(b, 0.5) - egui::vec2(0.0, 4.0),
+                    egui::Align2::CENTER_BOTTOM,
+                );
+            }
+            Command::Angle => {
+                let (a, b) = (points[0], points[1]);
+                let start = (a.1 - b.1).atan2(a.0 - b.0);
+                let radius = 25.0 / (self.zoom as f64 * 40.0);
+                let at = |angle: f64, radius: f64| {
+                    self.to_screen(
+                        rect,
+                        (b.0 + radius * angle.cos(), b.1 + radius * angle.sin()),
+                    )
+                };
+                let steps = 32;
+                let arc: Vec<Pos2> = (0..=steps)
+                    .map(|k| at(start + value * k as f64 / steps as f64, radius))
+                    .collect();
+
+                let mut mesh = egui::Mesh::default();
+                let fill = color.gamma_multiply(style.fill);
+                mesh.colored_vertex(self.to_screen(rect, b), fill);
+                for &pos in &arc {
+                    mesh.colored_vertex(pos, fill);
+                }
+                for k in 1..arc.len() as u32 {
+                    mesh.add_triangle(0, k, k + 1);
+                }
+                ui.painter().add(mesh);
+                for pair in arc.windows(2) {
+                    draw_styled_segment(ui, pair[0], pair[1], style.stroke(*color), style.line);
+                }
+                text(
+                    at(start + value / 2.0, radius * 1.8),
+                    egui::Align2::CENTER_CENTER,
+                );
+            }
+            Command::Area | Command::Perimeter => {
+                let center = match self.symbols.value(&shapes[0]) {
+                    Some(Value::Shape(Shape::Polygon(points))) => {
+                        let n = points.len() as f64;
+                        let (x, y) = points
+                            .iter()
+                            .fold((0.0, 0.0), |(x, y), p| (x + p.0, y + p.1));
+                        (x / n, y / n)
+                    }
+                    Some(Value::Shape(
+                        Shape::Circle { center, .. } | Shape::Ellipse { center, .. },
+                    )) => center,
+                    _ => return,
+                };
+                // An area and a perimeter of the same object sit one above
+                // the other.
+                let align = if *command == Command::Area {
+                    egui::Align2::CENTER_BOTTOM
+                } else {
+                    egui::Align2::CENTER_TOP
+                };
+                text(self.to_screen(rect, center), align);
+            }
+            _ => {}
+        }
+    }
+
+    /// The spacing and the points of a lattice over the view, a power of two
+    /// apart so that it stays put while panning and is between about 20 and
+    /// 40 pixels wide on screen.
+    fn lattice(&self, rect: egui::Rect) -> (f64, Vec<(f64, f64)>) {
+        let (left, top) = self.to_world(rect, rect.left_top());
+        let (right, bottom) = self.to_world(rect, rect.right_bottom());
+        let scale = (40.0 * self.zoom) as f64;
+        let spacing = 2f64.powf((30.0 / scale).log2().round());
+
+        let mut points = Vec::new();
+        for m in (left / spacing).ceil() as i64..=(right / spacing).floor() as i64 {
+            for n in (bottom / spacing).ceil() as i64..=(top / spacing).floor() as i64 {
+                points.push((m as f64 * spacing, n as f64 * spacing));
+            }
+        }
+        (spacing, points)
+    }
+
+    /// Draws the arrows of a vector field, scaled so that the longest one
+    /// in view nearly reaches the next lattice point.
+    fn draw_vector_field(&self, ui: &mut egui::Ui, rect: egui::Rect, i: usize) {
+        let (spacing, lattice) = self.lattice(rect);
+        let arrows: Vec<_> = lattice
+            .into_iter()
+            .filter_map(|point| Some((point, self.symbols.field(i, point)?)))
+            .filter(|(_, (u, v))| u.is_finite() && v.is_finite())
+            .collect();
+        let longest = arrows
+            .iter()
+            .map(|(_, (u, v))| u.hypot(*v))
+            .fold(0.0, f64::max);
+        if longest == 0.0 {
+            return;
+        }
+
+        let scale = 0.9 * spacing / longest;
+        let stroke = egui::Stroke::new(1.0, self.inputs[i].color);
+        for ((x, y), (u, v)) in arrows {
+            let (u, v) = (u * scale / 2.0, v * scale / 2.0);
+            let from = self.to_screen(rect, (x - u, y - v));
+            let to = self.to_screen(rect, (x + u, y + v));
+            if from.distance(to) >= 1.0 {
+                ui.painter().arrow(from, to - from, stroke);
+            }
+        }
+    }
+
+    /// Draws a vector as an arrow from its anchor, labelled with its name.
+    fn draw_vector(&self, ui: &mut egui::Ui, rect: egui::Rect, i: usize) {
+        let (Ok(Definition::Vector { name, .. }), Some(((ax, ay), (x, y)))) =
+            (self.symbols.get(i), self.symbols.vector(i))
+        else {
+            return;
+        };
+        let color = self.inputs[i].color;
+        let from = self.to_screen(rect, (ax, ay));
+        let to = self.to_screen(rect, (ax + x, ay + y));
+        draw_arrow(ui, from, to, egui::Stroke::new(2.0, color));
+        ui.painter().text(
+            from.lerp(to, 0.5) + egui::vec2(8.0, -8.0),
+            egui::Align2::LEFT_BOTTOM,
+            name,
+            egui::FontId::default(),
+            color,
+        );
+    }
+
+    /// Draws the direction field of a differential equation, as slope marks
+    /// for `dy/dx = f(x, y)` and as arrows for a system, on a lattice that
+    /// stays put while panning. Every seed gets the solution through it.
+    fn draw_differential(&self, ui: &mut egui::Ui, rect: egui::Rect, i: usize) {
+        let Ok(Definition::Differential { dx, .. }) = self.symbols.get(i) else {
+            return;
+        };
+        let system = dx.is_some();
+        let color = self.inputs[i].color;
+        let (left, top) = self.to_world(rect, rect.left_top());
+        let (right, bottom) = self.to_world(rect, rect.right_bottom());
+        let scale = (40.0 * self.zoom) as f64;
+
+        let (spacing, lattice) = self.lattice(rect);
+        let half = 0.35 * spacing;
+        let field_stroke = egui::Stroke::new(1.0, color.gamma_multiply(0.5));
+        for (x, y) in lattice {
+            let Some((u, v)) = self.symbols.field(i, (x, y)) else {
+                continue;
+            };
+            let length = u.hypot(v);
+            if !length.is_finite() || length == 0.0 {
+                continue;
+            }
+            let (u, v) = (u / length * half, v / length * half);
+            let from = self.to_screen(rect, (x - u, y - v));
+            let to = self.to_screen(rect, (x + u, y + v));
+            if system {
+                ui.painter().arrow(from, to - from, field_stroke);
+            } else {
+                ui.painter().line_segment([from, to], field_stroke);
+            }
+        }
+
+        // Solutions may leave the view and come back within this margin.
+        let (width, height) = (right - left, top - bottom);
+        let bounds = (
+            (left - width / 2.0, bottom - height / 2.0),
+            (right + width / 2.0, top + height / 2.0),
+        );
+        let stroke = egui::Stroke::new(1.5, color);
+        for &seed in &self.inputs[i].seeds {
+            let field = |x, y| self.symbols.field(i, (x, y));
+            let mut backward = ode::trace(field, seed, -1.0, bounds, 2.0 / scale);
+            let forward = ode::trace(field, seed, 1.0, bounds, 2.0 / scale);
+            backward.reverse();
+            backward.extend(forward.into_iter().skip(1));
+            let points: Vec<Pos2> = backward
+                .into_iter()
+                .map(|point| self.to_screen(rect, point))
+                .collect();
+            ui.painter().add(egui::Shape::line(points, stroke));
+            ui.painter()
+                .circle_filled(self.to_screen(rect, seed), 4.0, color);
+        }
+    }
+
+    fn draw_parametric(&self, ui: &mut egui::Ui, rect: egui::Rect, i: usize) {
+        let Some([x, y, start, end]) = self.symbols.compiled(i) else {
+            return;
+        };
+        let (Some(start), Some(end)) = (start.eval(&[]), end.eval(&[])) else {
+            return;
+        };
+        let samples = 2000;
+
+        let points = (0..=samples).map(|step| {
+            let t = start + (end - start) * step as f64 / samples as f64;
+            x.eval(&[t]).zip(y.eval(&[t]))
+        });
+        self.draw_path(ui, rect, points, self.inputs[i].color);
+    }
+
+    fn draw_polar(&self, ui: &mut egui::Ui, rect: egui::Rect, i: usize) {
+        let Some([radius, start, end]) = self.symbols.compiled(i) else {
+            return;
+        };
+        let (Some(start), Some(end)) = (start.eval(&[]), end.eval(&[])) else {
+            return;
+        };
+        let samples = 2000;
+
+        let points = (0..=samples).map(|step| {
+            let theta = start + (end - start) * step as f64 / samples as f64;
+            radius
+                .eval(&[theta])
+                .map(|r| (r * theta.cos(), r * theta.sin()))
+        });
+        self.draw_path(ui, rect, points, self.inputs[i].color);
+    }
+
+    /// Connects consecutive world points, breaking the path wherever a point
+    /// is missing or not finite.
+    fn draw_path(
+        &self,
+        ui: &mut egui::Ui,
+        rect: egui::Rect,
+        points: impl Iterator<Item = Option<(f64, f64)>>,
+        color: Color32,
+    ) {
+        let mut last_pos = None;
+
+        for point in points {
+            if let Some(point) = point.filter(|(x, y)| x.is_finite() && y.is_finite()) {
+                let pos = self.to_screen(rect, point);
+
+                if let Some(last) = last_pos {
+                    ui.painter()
+                        .line_segment([last, pos], egui::Stroke::new(1.0, color));
+                }
+
+                last_pos = Some(pos);
+            } else {
+                last_pos = None;
+            }
+        }
+    }
+
+    fn draw_point(
+        &self,
+        ui: &mut egui::Ui,
+        rect: egui::Rect,
+        name: &str,
+        (x, y): (f64, f64),
+        color: Color32,
+    ) {
+        let center_x = rect.center().x;
+        let center_y = rect.center().y;
+        let grid_unit = 40.0;
+
+        let screen_x = center_x + (x as f32 * self.zoom * grid_unit) + self.pan.x;
+        let screen_y = center_y - (y as f32 * self.zoom * grid_unit) + self.pan.y;
+
+        let pos = Pos2::new(screen_x, screen_y);
+
+        let point_radius = 5.0 * self.zoom;
+
+        ui.painter()
+            .circle(pos, point_radius, color, egui::Stroke::new(1.0, color));
+        ui.painter().text(
+            pos + Pos2::new(10.0 * self.zoom, 0.0).to_vec2(),
+            egui::Align2::CENTER_CENTER,
+            name,
+            egui::FontId::default(),
+            color,
+        );
+    }
+
+    /// Lets every point be dragged whose coordinates are plain numbers, and
+    /// writes where it was dragged to back into its input.
+    fn drag_points(&mut self, ui: &mut egui::Ui, rect: egui::Rect) {
+        let size = 7.0;
+        for i in 0..self.inputs.len() {
+            let Ok(Definition::Point { name, .. }) = self.symbols.get(i) else {
+                continue;
+            };
+            let Some(Value::Point(x, y)) = self.symbols.value(name) else {
+                continue;
+            };
+            let mut point = self.to_screen(rect, (x, y));
+            let response = ui.interact(
+                egui::Rect::from_center_size(point, egui::vec2(size * 2.0, size * 2.0)),
+                ui.id().with(("point", i)),
+                egui::Sense::click_and_drag(),
+            );
+            if !self.symbols.is_free_point(i) {
+                response.on_hover_text("The coordinates are expressions");
+                continue;
+            }
+
+            let response = response.on_hover_cursor(egui::CursorIcon::Grab);
+            if response.dragged() {
+                point += response.drag_delta();
+                let (mut x, mut y) = self.to_world(rect, point);
+                if let Some(pointer) = response
+                    .interact_pointer_pos()
+                    .filter(|_| self.snap_to_grid)
+                {
+                    let (px, py) = self.to_world(rect, pointer);
+                    (x, y) = (px.round(), py.round());
+                }
+                rewrite::rewrite_point(&mut self.inputs[i].input, (x, y));
+            }
+        }
+    }
+
+    /// Adds a solution through `point` to the last differential equation.
+    fn seed(&mut self, point: (f64, f64)) {
+        let last = (0..self.inputs.len())
+            .rev()
+            .find(|&i| matches!(self.symbols.get(i), Ok(Definition::Differential { .. })));
+        if let Some(i) = last {
+            self.inputs[i].seeds.push(point);
+        }
+    }
+
+    /// Lets the seeds of solution curves be dragged, and removed with a
+    /// right click.
+    fn drag_seeds(&mut self, ui: &mut egui::Ui, rect: egui::Rect) {
+        let size = 7.0;
+        for i in 0..self.inputs.len() {
+            if !matches!(self.symbols.get(i), Ok(Definition::Differential { .. })) {
+                continue;
+            }
+            let mut removed = None;
+            for k in 0..self.inputs[i].seeds.len() {
+                let point = self.to_screen(rect, self.inputs[i].seeds[k]);
+                let response = ui
+                    .interact(
+                        egui::Rect::from_center_size(point, egui::vec2(size * 2.0, size * 2.0)),
+                        ui.id().with(("seed", i, k)),
+                        egui::Sense::click_and_drag(),
+                    )
+                    .on_hover_cursor(egui::CursorIcon::Grab)
+                    .on_hover_text("Drag to move the solution, right click to remove it");
+                if response.secondary_clicked() {
+                    removed = Some(k);
+                } else if response.dragged() {
+                    let mut seed = self.to_world(rect, point + response.drag_delta());
+                    if let Some(pointer) = response
+                        .interact_pointer_pos()
+                        .filter(|_| self.snap_to_grid)
+                    {
+                        let (px, py) = self.to_world(rect, pointer);
+                        seed = (px.round(), py.round());
+                    }
+                    self.inputs[i].seeds[k] = seed;
+                }
+            }
+            if let Some(k) = removed {
+                self.inputs[i].seeds.remove(k);
+            }
+        }
+    }
+
+    fn evaluate_expression(&self, i: usize, x: f64) -> Option<f64> {
+        self.symbols.evaluate(i, &[x])
+    }
+
+    fn to_screen(&self, rect: egui::Rect, (x, y): (f64, f64)) -> Pos2 {
+        let grid_unit = 40.0;
+        Pos2::new(
+            rect.center().x + (x as f32 * self.zoom * grid_unit) + self.pan.x,
+            rect.center().y - (y as f32 * self.zoom * grid_unit) + self.pan.y,
+        )
+    }
+
+    fn to_world(&self, rect: egui::Rect, pos: Pos2) -> (f64, f64) {
+        let grid_unit = 40.0;
+        (
+            ((pos.x - rect.center().x - self.pan.x) / (self.zoom * grid_unit)) as f64,
+            ((rect.center().y + self.pan.y - pos.y) / (self.zoom * grid_unit)) as f64,
+        )
+    }
+}
+
+impl Default for Cartesian {
+    fn default() -> Self {
+        Self {
+            inputs: vec![],
+            symbols: SymbolTable::default(),
+            sliders: HashMap::new(),
+            side_bar_open: true,
+            zoom: 1.0,
+            pan: Pos2::ZERO,
+            axis_color: Color32::WHITE,
+            grid_color: Color32::from_gray(100),
+            polar_grid: false,
+            show_markers: true,
+            snap_to_grid: false,
+            complex_mode: false,
+            contours: false,
+            domain: DomainColoring::default(),
+            selected_marker: None,
+            switch: false,
+        }
+    }
+}
+
+/// Draws a line from `from` to `to` with a head at `to` whose size does not
+/// depend on the length of the line.
+fn draw_arrow(ui: &mut egui::Ui, from: Pos2, to: Pos2, stroke: egui::Stroke) {
+    let length = from.distance(to);
+    if length == 0.0 {
+        return;
+    }
+    let back = (from - to) / length * (10.0f32).min(length / 2.0);
+    let side = back.rot90() * 0.5;
+    ui.painter().line_segment([from, to], stroke);
+    ui.painter().add(egui::Shape::convex_polygon(
+        vec![to, to + back + side, to + back - side],
+        stroke.color,
+        egui::Stroke::NONE,
+    ));
+}
+
+/// Draws the part of `a..b` inside the clip rectangle that falls on the
+/// dashes of a pattern laid along the segment's dominant screen axis, so that
+/// consecutive segments of one curve line up into a single dashed line.
+fn draw_dashed_segment(ui: &mut egui::Ui, a: Pos2, b: Pos2, stroke: egui::Stroke, dash: f32) {
+    let horizontal = (b.x - a.x).abs() >= (b.y - a.y).abs();
+    let (from, to) = if horizontal { (a.x, b.x) } else { (a.y, b.y) };
+    if from == to {
+        return;
+    }
+
+    let clip = ui.clip_rect();
+    let (min, max) = if horizontal {
+        (clip.left(), clip.right())
+    } else {
+        (clip.top(), clip.bottom())
+    };
+    let (lo, hi) = (from.min(to).max(min - dash), from.max(to).min(max + dash));
+    let mut start = lo;
+    while start < hi {
+        let end = (((start / dash).floor() + 1.0) * dash).min(hi);
+        if ((start / dash).floor() as i64).rem_euclid(2) == 0 {
+            let (t0, t1) = ((start - from) / (to - from), (end - from) / (to - from));
+            ui.painter()
+                .line_segment([a.lerp(b, t0), a.lerp(b, t1)], stroke);
+        }
+        start = end;
+    }
+}
+
+fn draw_styled_segment(ui: &mut egui::Ui, a: Pos2, b: Pos2, stroke: egui::Stroke, line: LineStyle) {
+    match line {
+        LineStyle::Solid => {
+            ui.painter().line_segment([a, b], stroke);
+        }
+        LineStyle::Dashed => draw_dashed_segment(ui, a, b, stroke, 5.0),
+        LineStyle::Dotted => draw_dashed_segment(ui, a, b, stroke, 2.0),
+    }
+}
+
+/// The ends of the pieces of `f` at a boundary `x`, each closed if `f(x)`
+/// is the limit from that side. Sides that agree within `pixel` and with
+/// `f(x)` are one unbroken piece and have no ends; a value that is neither
+/// limit is a closed point of its own.
+fn ends_at(f: impl Fn(f64) -> Option<f64>, x: f64, pixel: f64) -> Vec<((f64, f64), bool)> {
+    let h = pixel * 1e-3;
+    let value = f(x);
+    let near = |a: f64, b: f64| (a - b).abs() < pixel / 2.0;
+    let limits = match (f(x - h), f(x + h)) {
+        (Some(l), Some(r)) if near(l, r) => vec![l],
+        (l, r) => l.into_iter().chain(r).collect(),
+    };
+    if let ([limit], Some(value)) = (&limits[..], value) {
+        if near(*limit, value) {
+            return Vec::new();
+        }
+    }
+    let mut ends: Vec<_> = limits
+        .iter()
+        .map(|&limit| ((x, limit), value.is_some_and(|v| near(v, limit))))
+        .collect();
+    if let Some(v) = value.filter(|&v| !limits.iter().any(|&limit| near(v, limit))) {
+        ends.push(((x, v), true));
+    }
+    ends
+}
+
+/// Explains why a line is not drawn: what it was taken for, the error, and
+/// where in the line it is.
+fn error_tooltip(ui: &mut egui::Ui, input: &str, kind: &str, error: &SymbolError) {
+    ui.strong(format!("Read as: {}", kind));
+    ui.label(error.to_string());
+    if let SymbolError::Undefined(names) = error {
+        ui.label(format!("Unknown identifiers: {}", names.join(", ")));
+    }
+    if let Some(position) = error.position(input) {
+        let column = input[..position].chars().count();
+        ui.label(format!("At column {}:", column + 1));
+        ui.label(egui::RichText::new(format!("{}\n{}^", input, " ".repeat(column))).monospace());
+    }
+}
+
+/// A measured value as it is shown, with angles in degrees.
+fn measurement_label(command: Command, value: f64) -> String {
+    if command == Command::Angle {
+        format!("{:.2}°", value.to_degrees())
+    } else {
+        format!("{:.4}", value)
+    }
+}
+
+/// The equation of the line through `(x, y)` with the given slope.
+fn line_equation((x, y): (f64, f64), slope: f64) -> String {
+    if !slope.is_finite() {
+        return format!("x = {:.4}", x);
+    }
+    // Adding zero turns -0 into 0.
+    let slope = slope + 0.0;
+    let intercept = y - slope * x + 0.0;
+    let sign = if intercept < 0.0 { '-' } else { '+' };
+    format!("y = {:.4}x {} {:.4}", slope, sign, intercept.abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ends_follow_the_values_at_a_boundary() {
+        let pixel = 0.01;
+        // `{x < 0: -x, x}` is defined at 0 by the fallback piece.
+        let abs = |x: f64| Some(if x < 0.0 { -x } else { x });
+        assert!(ends_at(abs, 0.0, pixel).is_empty());
+
+        // `{x < 2: 1, x < 1: 5}` never uses its second piece.
+        let step = |x: f64| (x < 2.0).then_some(1.0);
+        assert!(ends_at(step, 1.0, pixel).is_empty());
+        assert_eq!(ends_at(step, 2.0, pixel), vec![((2.0, 1.0), false)]);
+
+        let jump = |x: f64| Some(if x < 0.0 { 0.0 } else { 1.0 });
+        assert_eq!(
+            ends_at(jump, 0.0, pixel),
+            vec![((0.0, 0.0), false), ((0.0, 1.0), true)]
+        );
+
+        let hole = |x: f64| (x != 0.0).then_some(1.0);
+        assert_eq!(ends_at(hole, 0.0, pixel), vec![((0.0, 1.0), false)]);
+
+        // A single value away from both limits is a point of its own.
+        let lifted = |x: f64| Some(if x == 0.0 { 3.0 } else { 1.0 });
+        assert_eq!(
+            ends_at(lifted, 0.0, pixel),
+            vec![((0.0, 1.0), false), ((0.0, 3.0), true)]
+        );
+    }
+}
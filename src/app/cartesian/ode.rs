@@ -0,0 +1,162 @@
+/// Longest solution traced in either direction, in steps.
+const MAX_STEPS: usize = 2000;
+
+/// Traces the path through `start` along the vector field `field`, forward
+/// when `direction` is 1 and backward when it is -1, until it leaves the box
+/// `min..max`, closes into an orbit, runs into a point where the field
+/// vanishes or is undefined, or reaches `MAX_STEPS`.
+///
+/// The field is followed by arc length with the Dormand–Prince RK45 pair,
+/// so the solution speeds up or slows down without changing the path, and
+/// no step is longer than `step`.
+pub fn trace(
+    field: impl Fn(f64, f64) -> Option<(f64, f64)>,
+    start: (f64, f64),
+    direction: f64,
+    (min, max): ((f64, f64), (f64, f64)),
+    step: f64,
+) -> Vec<(f64, f64)> {
+    let unit = |(x, y): (f64, f64)| {
+        let (u, v) = field(x, y)?;
+        let length = u.hypot(v);
+        (length.is_finite() && length > 1e-12)
+            .then(|| (direction * u / length, direction * v / length))
+    };
+    let inside = |(x, y): (f64, f64)| x >= min.0 && x <= max.0 && y >= min.1 && y <= max.1;
+    let tolerance = step * 1e-3;
+
+    let mut points = vec![start];
+    let mut p = start;
+    let mut h = step;
+    let mut travelled = 0.0;
+    let mut last = (0.0, 0.0);
+    while points.len() < MAX_STEPS && inside(p) {
+        let Some((next, error)) = dormand_prince(&unit, p, h) else {
+            break;
+        };
+        if error > tolerance {
+            h *= (0.9 * (tolerance / error).powf(0.2)).max(0.2);
+            if h < step * 1e-4 {
+                break;
+            }
+            continue;
+        }
+        let moved = (next.0 - p.0, next.1 - p.1);
+        // Turning back means the path stepped over a fixed point.
+        if moved.0 * last.0 + moved.1 * last.1 < 0.0 {
+            break;
+        }
+        last = moved;
+        p = next;
+        points.push(p);
+        travelled += h;
+        if travelled > 4.0 * step && distance(p, start) < step {
+            points.push(start);
+            break;
+        }
+        let grow = if error == 0.0 {
+            5.0
+        } else {
+            (0.9 * (tolerance / error).powf(0.2)).clamp(0.2, 5.0)
+        };
+        h = (h * grow).min(step);
+    }
+    points
+}
+
+/// One step of length `h` from `p`, with the fifth order result and its
+/// distance from the embedded fourth order one.
+fn dormand_prince(
+    f: &impl Fn((f64, f64)) -> Option<(f64, f64)>,
+    p: (f64, f64),
+    h: f64,
+) -> Option<((f64, f64), f64)> {
+    const A: [&[f64]; 6] = [
+        &[1.0 / 5.0],
+        &[3.0 / 40.0, 9.0 / 40.0],
+        &[44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0],
+        &[
+            19372.0 / 6561.0,
+            -25360.0 / 2187.0,
+            64448.0 / 6561.0,
+            -212.0 / 729.0,
+        ],
+        &[
+            9017.0 / 3168.0,
+            -355.0 / 33.0,
+            46732.0 / 5247.0,
+            49.0 / 176.0,
+            -5103.0 / 18656.0,
+        ],
+        &[
+            35.0 / 384.0,
+            0.0,
+            500.0 / 1113.0,
+            125.0 / 192.0,
+            -2187.0 / 6784.0,
+            11.0 / 84.0,
+        ],
+    ];
+    // Weights of the fifth order result minus those of the fourth order one.
+    const E: [f64; 7] = [
+        71.0 / 57600.0,
+        0.0,
+        -71.0 / 16695.0,
+        71.0 / 1920.0,
+        -17253.0 / 339200.0,
+        22.0 / 525.0,
+        -1.0 / 40.0,
+    ];
+
+    let mut k = [(0.0, 0.0); 7];
+    k[0] = f(p)?;
+    for (s, row) in A.iter().enumerate() {
+        let (mut x, mut y) = p;
+        for (a, (u, v)) in row.iter().zip(&k) {
+            x += h * a * u;
+            y += h * a * v;
+        }
+        k[s + 1] = f((x, y))?;
+    }
+    // The last stage is taken at the fifth order result itself.
+    let (mut x, mut y) = p;
+    for (a, (u, v)) in A[5].iter().zip(&k) {
+        x += h * a * u;
+        y += h * a * v;
+    }
+    let (mut ex, mut ey) = (0.0, 0.0);
+    for (e, (u, v)) in E.iter().zip(&k) {
+        ex += h * e * u;
+        ey += h * e * v;
+    }
+    Some(((x, y), ex.hypot(ey)))
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circular_orbits_close() {
+        let bounds = ((-2.0, -2.0), (2.0, 2.0));
+        let orbit = trace(|x, y| Some((-y, x)), (1.0, 0.0), 1.0, bounds, 0.05);
+        assert!(orbit.len() < MAX_STEPS);
+        assert_eq!(orbit.last(), Some(&(1.0, 0.0)));
+        assert!(orbit.iter().all(|&(x, y)| (x.hypot(y) - 1.0).abs() < 1e-3));
+        // Once around, give or take the last step.
+        let length: f64 = orbit.windows(2).map(|w| distance(w[0], w[1])).sum();
+        assert!((length - std::f64::consts::TAU).abs() < 0.1);
+    }
+
+    #[test]
+    fn paths_stop_at_the_edge() {
+        let bounds = ((-1.0, -1.0), (1.0, 1.0));
+        let path = trace(|_, _| Some((1.0, 0.0)), (0.0, 0.0), -1.0, bounds, 0.1);
+        let &(x, y) = path.last().unwrap();
+        assert!(x < -1.0 && x > -1.2 && y == 0.0);
+    }
+}
@@ -0,0 +1,53 @@
+use exmex::prelude::*;
+use exmex::{BinOp, ExResult, FloatOpsFactory, MakeOperators, Operator};
+
+/// exmex's float operators plus comparisons, which are 1 where they hold and
+/// 0 where they do not, and the operators that piecewise functions are
+/// written with.
+#[derive(Clone, Debug)]
+pub struct Ops;
+
+pub type Expr = FlatEx<f64, Ops>;
+
+pub fn parse(source: &str) -> ExResult<Expr> {
+    Expr::parse(source)
+}
+
+/// 1 or 0 for whether a comparison of `a` and `b` holds, undefined if either
+/// is.
+fn truth(holds: bool, a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else if holds {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+impl MakeOperators<f64> for Ops {
+    fn make<'a>() -> Vec<Operator<'a, f64>> {
+        let binary = |repr, apply| {
+            Operator::make_bin(
+                repr,
+                BinOp {
+                    apply,
+                    prio: 0,
+                    is_commutative: false,
+                },
+            )
+        };
+        let mut ops = FloatOpsFactory::<f64>::make();
+        ops.extend([
+            binary("lt", |a, b| truth(a < b, a, b)),
+            binary("le", |a, b| truth(a <= b, a, b)),
+            binary("gt", |a, b| truth(a > b, a, b)),
+            binary("ge", |a, b| truth(a >= b, a, b)),
+            // `when(c, v)` is `v` where `c` holds and undefined elsewhere.
+            binary("when", |c, v| if c == 1.0 { v } else { f64::NAN }),
+            // `otherwise(a, b)` is `a` where it is defined and `b` elsewhere.
+            binary("otherwise", |a, b| if a.is_nan() { b } else { a }),
+        ]);
+        ops
+    }
+}
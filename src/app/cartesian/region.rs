@@ -0,0 +1,68 @@
+/// Covers the part of the rectangle `min..max` where `inside` holds with
+/// world-space rectangles. The rectangle is split into `cells` coarse cells
+/// and the ones on the border of the region are subdivided `depth` times.
+pub fn fill(
+    inside: impl Fn(f64, f64) -> bool,
+    min: (f64, f64),
+    max: (f64, f64),
+    cells: (usize, usize),
+    depth: u32,
+) -> Vec<[(f64, f64); 2]> {
+    let (nx, ny) = cells;
+    let dx = (max.0 - min.0) / nx as f64;
+    let dy = (max.1 - min.1) / ny as f64;
+
+    let mut grid = vec![false; (nx + 1) * (ny + 1)];
+    for j in 0..=ny {
+        for i in 0..=nx {
+            grid[j * (nx + 1) + i] = inside(min.0 + i as f64 * dx, min.1 + j as f64 * dy);
+        }
+    }
+
+    let mut rects = Vec::new();
+    for j in 0..ny {
+        for i in 0..nx {
+            let corners = [
+                grid[j * (nx + 1) + i],
+                grid[j * (nx + 1) + i + 1],
+                grid[(j + 1) * (nx + 1) + i + 1],
+                grid[(j + 1) * (nx + 1) + i],
+            ];
+            let origin = (min.0 + i as f64 * dx, min.1 + j as f64 * dy);
+            refine(&inside, origin, (dx, dy), corners, depth, &mut rects);
+        }
+    }
+    rects
+}
+
+fn refine(
+    inside: &impl Fn(f64, f64) -> bool,
+    (x, y): (f64, f64),
+    (w, h): (f64, f64),
+    corners: [bool; 4],
+    depth: u32,
+    rects: &mut Vec<[(f64, f64); 2]>,
+) {
+    let center = inside(x + w / 2.0, y + h / 2.0);
+    if corners.iter().all(|&c| c == center) || depth == 0 {
+        if center {
+            rects.push([(x, y), (x + w, y + h)]);
+        }
+        return;
+    }
+
+    let (hw, hh) = (w / 2.0, h / 2.0);
+    let bottom = inside(x + hw, y);
+    let right = inside(x + w, y + hh);
+    let top = inside(x + hw, y + h);
+    let left = inside(x, y + hh);
+    let quadrants = [
+        ((x, y), [corners[0], bottom, center, left]),
+        ((x + hw, y), [bottom, corners[1], right, center]),
+        ((x + hw, y + hh), [center, right, corners[2], top]),
+        ((x, y + hh), [left, center, top, corners[3]]),
+    ];
+    for (origin, corners) in quadrants {
+        refine(inside, origin, (hw, hh), corners, depth - 1, rects);
+    }
+}
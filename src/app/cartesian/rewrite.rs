@@ -0,0 +1,102 @@
+fn format_value(value: f64) -> String {
+    let formatted = format!("{:.6}", value);
+    let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+    if formatted == "-0" {
+        "0".to_string()
+    } else {
+        formatted.to_string()
+    }
+}
+
+/// Replaces the right-hand side of a definition with `value`, keeping the
+/// left-hand side and the spacing after `=` as the user typed them.
+fn rewrite_rhs(input: &mut String, value: &str) {
+    if let Some((lhs, rhs)) = input.split_once('=') {
+        let space = &rhs[..rhs.len() - rhs.trim_start().len()];
+        *input = format!("{}={}{}", lhs, space, value);
+    }
+}
+
+/// Replaces the value of a variable definition such as `a = 2`.
+pub fn rewrite_value(input: &mut String, value: f64) {
+    rewrite_rhs(input, &format_value(value));
+}
+
+/// Replaces the coordinates of a point definition such as `A = (1, 2)`.
+pub fn rewrite_point(input: &mut String, (x, y): (f64, f64)) {
+    rewrite_rhs(
+        input,
+        &format!("({}, {})", format_value(x), format_value(y)),
+    );
+}
+
+/// Replaces the last argument of a command such as `tangent(f, 1)` with
+/// `value`.
+pub fn rewrite_last_argument(input: &mut String, value: f64) {
+    let Some(close) = input.rfind(')') else {
+        return;
+    };
+    let mut depth = 0;
+    for (i, c) in input[..close].char_indices().rev() {
+        match c {
+            ')' => depth += 1,
+            '(' if depth == 0 => break,
+            '(' => depth -= 1,
+            ',' if depth == 0 => {
+                input.replace_range(i + 1..close, &format!(" {}", format_value(value)));
+                return;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewritten(input: &str, rewrite: impl Fn(&mut String)) -> String {
+        let mut input = input.to_string();
+        rewrite(&mut input);
+        input
+    }
+
+    #[test]
+    fn values_keep_their_spacing() {
+        assert_eq!(rewritten("a = 2", |s| rewrite_value(s, 1.5)), "a = 1.5");
+        assert_eq!(rewritten("a=2", |s| rewrite_value(s, -0.25)), "a=-0.25");
+        assert_eq!(
+            rewritten("b =  1", |s| rewrite_value(s, 1.0 / 3.0)),
+            "b =  0.333333"
+        );
+        assert_eq!(rewritten("c = 1", |s| rewrite_value(s, -1e-9)), "c = 0");
+    }
+
+    #[test]
+    fn points_keep_their_spacing() {
+        assert_eq!(
+            rewritten("A = (1, 2)", |s| rewrite_point(s, (3.0, -4.5))),
+            "A = (3, -4.5)"
+        );
+        assert_eq!(
+            rewritten("A=(1,2)", |s| rewrite_point(s, (0.0, 1.0))),
+            "A=(0, 1)"
+        );
+    }
+
+    #[test]
+    fn last_arguments_are_replaced() {
+        let rewrite = |s: &mut String| rewrite_last_argument(s, 2.5);
+        assert_eq!(rewritten("tangent(f, 1)", rewrite), "tangent(f, 2.5)");
+        assert_eq!(rewritten("tangent(f,1)", rewrite), "tangent(f, 2.5)");
+        assert_eq!(
+            rewritten("tangent(sin(2x), max(1, 2))", rewrite),
+            "tangent(sin(2x), 2.5)"
+        );
+        assert_eq!(
+            rewritten("tangent(f, (1 + 2)*3)", rewrite),
+            "tangent(f, 2.5)"
+        );
+        assert_eq!(rewritten("sin(x)", rewrite), "sin(x)");
+    }
+}
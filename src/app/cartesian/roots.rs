@@ -0,0 +1,129 @@
+/// Zeros of `f` in `from..to`, bracketed by sampling `samples` intervals and
+/// refined with Brent's method. Each root comes with whether `f` rises
+/// through it. Sign changes where `f` grows instead of vanishing are poles
+/// and are skipped.
+pub fn find_roots(
+    f: impl Fn(f64) -> Option<f64>,
+    from: f64,
+    to: f64,
+    samples: usize,
+) -> Vec<(f64, bool)> {
+    let f = |x: f64| f(x).filter(|y| y.is_finite());
+    let step = (to - from) / samples as f64;
+    let tolerance = step * 1e-9;
+    let mut roots = Vec::new();
+    let mut last = (from, f(from));
+    for k in 1..=samples {
+        let x = from + step * k as f64;
+        let y = f(x);
+        // Exact zeros are stepped over so that the bracket spans both sides
+        // and a flat stretch of zeros is not taken for a root.
+        if y == Some(0.0) {
+            continue;
+        }
+        if let ((a, Some(fa)), Some(fb)) = (last, y) {
+            if fa != 0.0 && (fa < 0.0) != (fb < 0.0) {
+                if let Some(root) = brent(f, a, x, tolerance) {
+                    let small = f(root).is_some_and(|y| y.abs() <= fa.abs().max(fb.abs()));
+                    if small {
+                        roots.push((root, fa < fb));
+                    }
+                }
+            }
+        }
+        last = (x, y);
+    }
+    roots
+}
+
+/// Root of `f` in a bracket `a..b` over which it changes sign.
+pub fn brent(f: impl Fn(f64) -> Option<f64>, a: f64, b: f64, tolerance: f64) -> Option<f64> {
+    let (mut a, mut b) = (a, b);
+    let (mut fa, mut fb) = (f(a)?, f(b)?);
+    if fa * fb > 0.0 {
+        return None;
+    }
+    if fa.abs() < fb.abs() {
+        std::mem::swap(&mut a, &mut b);
+        std::mem::swap(&mut fa, &mut fb);
+    }
+
+    let (mut c, mut fc) = (a, fa);
+    let mut d = a;
+    let mut bisected = true;
+    for _ in 0..100 {
+        if fb == 0.0 || (b - a).abs() <= tolerance {
+            return Some(b);
+        }
+
+        let mut s = if fa != fc && fb != fc {
+            // Inverse quadratic interpolation.
+            a * fb * fc / ((fa - fb) * (fa - fc))
+                + b * fa * fc / ((fb - fa) * (fb - fc))
+                + c * fa * fb / ((fc - fa) * (fc - fb))
+        } else {
+            b - fb * (b - a) / (fb - fa)
+        };
+
+        let bound = (3.0 * a + b) / 4.0;
+        let outside = !(bound.min(b)..=bound.max(b)).contains(&s);
+        let slow = if bisected {
+            (s - b).abs() >= (b - c).abs() / 2.0 || (b - c).abs() < tolerance
+        } else {
+            (s - b).abs() >= (c - d).abs() / 2.0 || (c - d).abs() < tolerance
+        };
+        bisected = outside || slow;
+        if bisected {
+            s = (a + b) / 2.0;
+        }
+
+        let fs = f(s)?;
+        d = c;
+        (c, fc) = (b, fb);
+        if fa * fs < 0.0 {
+            (b, fb) = (s, fs);
+        } else {
+            (a, fa) = (s, fs);
+        }
+        if fa.abs() < fb.abs() {
+            std::mem::swap(&mut a, &mut b);
+            std::mem::swap(&mut fa, &mut fb);
+        }
+    }
+    Some(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roots_are_found_with_their_direction() {
+        let roots = find_roots(|x| Some(x * x - 2.0), -3.0, 3.0, 100);
+        let sqrt2 = 2f64.sqrt();
+        assert_eq!(roots.len(), 2);
+        assert!((roots[0].0 + sqrt2).abs() < 1e-9 && !roots[0].1);
+        assert!((roots[1].0 - sqrt2).abs() < 1e-9 && roots[1].1);
+    }
+
+    #[test]
+    fn poles_are_not_roots() {
+        assert!(find_roots(|x| Some(1.0 / x), -1.0, 1.0, 99).is_empty());
+        // tan changes sign at its zeros and at its poles, only the zeros count.
+        let roots = find_roots(|x| Some(x.tan()), -2.0, 4.0, 100);
+        assert_eq!(roots.len(), 2);
+        assert!(roots[0].0.abs() < 1e-9);
+        assert!((roots[1].0 - std::f64::consts::PI).abs() < 1e-9);
+    }
+
+    #[test]
+    fn brent_converges_in_a_bracket() {
+        let root = brent(|x| Some(x.cos() - x), 0.0, 1.0, 1e-12).unwrap();
+        assert!((root.cos() - root).abs() < 1e-10);
+        assert_eq!(brent(|x| Some(x * x + 1.0), -1.0, 1.0, 1e-12), None);
+        assert_eq!(
+            brent(|x| (x > 0.0).then_some(x - 0.5), -1.0, 1.0, 1e-12),
+            None
+        );
+    }
+}
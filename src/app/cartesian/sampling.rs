@@ -0,0 +1,187 @@
+/// Subdivision levels below the initial sample spacing.
+const MAX_DEPTH: u32 = 12;
+
+/// The sampled graph of a function: polylines broken at poles, jumps and
+/// the edges of its domain, and the holes where it is undefined at a single
+/// point but has a limit.
+#[derive(Default)]
+pub struct Graph {
+    pub paths: Vec<Vec<(f64, f64)>>,
+    pub holes: Vec<(f64, f64)>,
+}
+
+/// Samples the graph of `f` over `from..to`, where `pixel` is the world size
+/// of a screen pixel. Intervals are halved until the graph is straight to
+/// within half a pixel over them.
+pub fn sample(f: impl Fn(f64) -> Option<f64>, from: f64, to: f64, pixel: f64) -> Graph {
+    let mut sampler = Sampler {
+        f: |x: f64| f(x).filter(|y| y.is_finite()),
+        pixel,
+        budget: 50_000,
+        path: Vec::new(),
+        graph: Graph::default(),
+    };
+
+    // Samples on a power of two grid land exactly on integers and simple
+    // fractions, where holes such as the one of `sin(x)/x` tend to be.
+    let step = 2f64.powf((4.0 * pixel).log2().floor());
+    let (start, end) = ((from / step).floor() as i64, (to / step).ceil() as i64);
+    let mut last = None;
+    for k in start..=end {
+        let x = k as f64 * step;
+        let next = (x, sampler.value(x));
+        match last {
+            Some(last) => sampler.segment(last, next, MAX_DEPTH),
+            None => sampler.path.extend(next.1.map(|y| (x, y))),
+        }
+        last = Some(next);
+    }
+    sampler.flush();
+    sampler.graph
+}
+
+struct Sampler<F> {
+    f: F,
+    pixel: f64,
+    /// Evaluations left before intervals are accepted as they are.
+    budget: u32,
+    path: Vec<(f64, f64)>,
+    graph: Graph,
+}
+
+impl<F: Fn(f64) -> Option<f64>> Sampler<F> {
+    fn eval(&mut self, x: f64) -> Option<f64> {
+        self.budget = self.budget.saturating_sub(1);
+        (self.f)(x)
+    }
+
+    /// The value of the function at `x`, or its limit there if `x` is a hole.
+    fn value(&mut self, x: f64) -> Option<f64> {
+        if let Some(y) = self.eval(x) {
+            return Some(y);
+        }
+        let epsilon = self.pixel * 1e-4;
+        let (left, right) = (self.eval(x - epsilon)?, self.eval(x + epsilon)?);
+        if (left - right).abs() > self.pixel {
+            return None;
+        }
+        let y = (left + right) / 2.0;
+        self.graph.holes.push((x, y));
+        Some(y)
+    }
+
+    /// Extends the current path from `a`, which it ends at if defined, to `b`.
+    fn segment(&mut self, a: (f64, Option<f64>), b: (f64, Option<f64>), depth: u32) {
+        let ((xa, ya), (xb, yb)) = (a, b);
+        let (fa, fb) = match (ya, yb) {
+            (Some(fa), Some(fb)) => (fa, fb),
+            (Some(_), None) => {
+                if let Some((xe, ye)) = self.edge(xa, xb) {
+                    self.segment(a, (xe, Some(ye)), depth);
+                }
+                self.flush();
+                return;
+            }
+            (None, Some(_)) => {
+                if let Some((xe, ye)) = self.edge(xb, xa) {
+                    self.path.push((xe, ye));
+                    self.segment((xe, Some(ye)), b, depth);
+                } else {
+                    self.path.extend(yb.map(|yb| (xb, yb)));
+                }
+                return;
+            }
+            (None, None) => return,
+        };
+
+        let xm = (xa + xb) / 2.0;
+        if xm <= xa || xm >= xb || self.budget == 0 {
+            self.path.push((xb, fb));
+            return;
+        }
+        let ym = self.value(xm);
+        let straight = ym.is_some_and(|fm| (fm - (fa + fb) / 2.0).abs() <= self.pixel / 2.0);
+        if straight && depth < MAX_DEPTH {
+            self.path.push((xb, fb));
+        } else if depth > 0 {
+            self.segment(a, (xm, ym), depth - 1);
+            self.segment((xm, ym), b, depth - 1);
+        } else if ym.is_some() && self.continuous((xa, fa), (xb, fb)) {
+            self.path.push((xb, fb));
+        } else {
+            self.flush();
+            self.path.push((xb, fb));
+        }
+    }
+
+    /// Whether the change from `a` to `b` shrinks below a pixel when it is
+    /// followed into ever smaller halves, rather than staying in one point
+    /// as it does at a jump or a pole.
+    fn continuous(&mut self, (mut xa, mut fa): (f64, f64), (mut xb, mut fb): (f64, f64)) -> bool {
+        for _ in 0..60 {
+            if (fb - fa).abs() <= self.pixel {
+                return true;
+            }
+            let xm = (xa + xb) / 2.0;
+            if xm <= xa || xm >= xb {
+                return false;
+            }
+            let Some(fm) = self.eval(xm) else {
+                return false;
+            };
+            if (fm - fa).abs() > (fb - fm).abs() {
+                (xb, fb) = (xm, fm);
+            } else {
+                (xa, fa) = (xm, fm);
+            }
+        }
+        false
+    }
+
+    /// The last point from `inside` towards `outside` where the function is
+    /// still defined.
+    fn edge(&mut self, mut inside: f64, mut outside: f64) -> Option<(f64, f64)> {
+        let mut found = None;
+        for _ in 0..40 {
+            let mid = (inside + outside) / 2.0;
+            match self.eval(mid) {
+                Some(y) => {
+                    inside = mid;
+                    found = Some((mid, y));
+                }
+                None => outside = mid,
+            }
+        }
+        found
+    }
+
+    fn flush(&mut self) {
+        let path = std::mem::take(&mut self.path);
+        if path.len() > 1 {
+            self.graph.paths.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poles_split_the_graph() {
+        let graph = sample(|x| Some(1.0 / x), -2.0, 2.0, 0.01);
+        assert_eq!(graph.paths.len(), 2);
+        assert!(graph.paths[0].iter().all(|&(x, _)| x < 0.0));
+        assert!(graph.paths[1].iter().all(|&(x, _)| x > 0.0));
+        assert!(graph.holes.is_empty());
+    }
+
+    #[test]
+    fn removable_singularities_are_holes() {
+        let graph = sample(|x| Some(x.sin() / x), -5.0, 5.0, 0.01);
+        assert_eq!(graph.holes.len(), 1);
+        let (x, y) = graph.holes[0];
+        assert_eq!(x, 0.0);
+        assert!((y - 1.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,156 @@
+use eframe::egui;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Playback {
+    Loop,
+    Bounce,
+}
+
+/// Range, step and animation settings of the slider shown for a numeric
+/// variable such as `a=2`.
+pub struct Slider {
+    min: f64,
+    max: f64,
+    step: f64,
+    pub playing: bool,
+    playback: Playback,
+    /// Full sweeps from `min` to `max` every five seconds.
+    speed: f64,
+    direction: f64,
+}
+
+impl Slider {
+    pub fn new(value: f64) -> Self {
+        Self {
+            min: value.floor().min(-10.0),
+            max: value.ceil().max(10.0),
+            step: 0.1,
+            playing: false,
+            playback: Playback::Loop,
+            speed: 1.0,
+            direction: 1.0,
+        }
+    }
+
+    /// Moves `value` forward by `dt` seconds of animation.
+    pub fn advance(&mut self, value: f64, dt: f64) -> f64 {
+        let span = self.max - self.min;
+        if span <= 0.0 {
+            return self.min;
+        }
+
+        let next = value + self.direction * self.speed * span / 5.0 * dt;
+        match self.playback {
+            Playback::Loop if next > self.max => self.min + (next - self.max) % span,
+            Playback::Loop if next < self.min => self.max - (self.min - next) % span,
+            Playback::Bounce if next > self.max => {
+                self.direction = -1.0;
+                self.max - (next - self.max) % span
+            }
+            Playback::Bounce if next < self.min => {
+                self.direction = 1.0;
+                self.min + (self.min - next) % span
+            }
+            _ => next,
+        }
+    }
+
+    /// Shows the slider and its settings. Returns whether `value` was changed
+    /// by the user.
+    pub fn ui(&mut self, ui: &mut egui::Ui, id: usize, value: &mut f64) -> bool {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            let play = if self.playing { "⏸" } else { "▶" };
+            if ui
+                .add_sized([20.0, 20.0], egui::Button::new(play))
+                .on_hover_text("Play or pause the animation")
+                .clicked()
+            {
+                self.playing = !self.playing;
+            }
+            let response = ui.add(
+                egui::Slider::new(value, self.min..=self.max)
+                    .step_by(self.step)
+                    .show_value(false),
+            );
+            // The slider also snaps values it is shown to its steps, which
+            // must not overwrite a value that was typed or animated.
+            changed = response.changed()
+                && (response.dragged()
+                    || response.drag_stopped()
+                    || response.clicked()
+                    || response.has_focus());
+        });
+
+        egui::CollapsingHeader::new("Slider settings")
+            .id_salt(("slider", id))
+            .show(ui, |ui| {
+                egui::Grid::new(("slider_grid", id)).show(ui, |ui| {
+                    ui.label("Min:");
+                    ui.add(egui::DragValue::new(&mut self.min).speed(self.step));
+                    ui.end_row();
+
+                    ui.label("Max:");
+                    ui.add(egui::DragValue::new(&mut self.max).speed(self.step));
+                    ui.end_row();
+
+                    ui.label("Step:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.step)
+                            .speed(0.01)
+                            .range(0.001..=f64::MAX),
+                    );
+                    ui.end_row();
+
+                    ui.label("Speed:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.speed)
+                            .speed(0.05)
+                            .range(0.05..=20.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Mode:");
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut self.playback, Playback::Loop, "Loop");
+                        ui.selectable_value(&mut self.playback, Playback::Bounce, "Bounce");
+                    });
+                    ui.end_row();
+                });
+            });
+        self.max = self.max.max(self.min);
+
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loop_wraps_to_the_other_end() {
+        // From -10 to 10, swept in five seconds: 4 per second.
+        let mut slider = Slider::new(0.0);
+        assert_eq!(slider.advance(9.0, 1.0), -7.0);
+        assert_eq!(slider.advance(-9.0, -1.0), 7.0);
+        assert_eq!(slider.advance(1.0, 0.5), 3.0);
+    }
+
+    #[test]
+    fn bounce_turns_around_at_the_ends() {
+        let mut slider = Slider::new(0.0);
+        slider.playback = Playback::Bounce;
+        assert_eq!(slider.advance(9.0, 1.0), 7.0);
+        assert_eq!(slider.direction, -1.0);
+        assert_eq!(slider.advance(-9.0, 1.0), -7.0);
+        assert_eq!(slider.direction, 1.0);
+    }
+
+    #[test]
+    fn empty_range_stays_at_min() {
+        let mut slider = Slider::new(0.0);
+        slider.max = slider.min;
+        assert_eq!(slider.advance(3.0, 1.0), slider.min);
+    }
+}
@@ -0,0 +1,64 @@
+use eframe::egui::{self, Color32};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LineStyle {
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+/// How a geometric object is drawn.
+pub struct Style {
+    pub width: f32,
+    pub line: LineStyle,
+    /// Opacity of the inside of circles and polygons.
+    pub fill: f32,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            width: 1.5,
+            line: LineStyle::Solid,
+            fill: 0.2,
+        }
+    }
+}
+
+impl Style {
+    pub fn stroke(&self, color: Color32) -> egui::Stroke {
+        egui::Stroke::new(self.width, color)
+    }
+
+    /// Shows the style settings, with the fill only for objects that have an
+    /// inside.
+    pub fn ui(&mut self, ui: &mut egui::Ui, id: usize, filled: bool) {
+        egui::CollapsingHeader::new("Style")
+            .id_salt(("style", id))
+            .show(ui, |ui| {
+                egui::Grid::new(("style_grid", id)).show(ui, |ui| {
+                    ui.label("Width:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.width)
+                            .speed(0.1)
+                            .range(0.5..=8.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Line:");
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut self.line, LineStyle::Solid, "Solid");
+                        ui.selectable_value(&mut self.line, LineStyle::Dashed, "Dashed");
+                        ui.selectable_value(&mut self.line, LineStyle::Dotted, "Dotted");
+                    });
+                    ui.end_row();
+
+                    if filled {
+                        ui.label("Fill:");
+                        ui.add(egui::Slider::new(&mut self.fill, 0.0..=1.0));
+                        ui.end_row();
+                    }
+                });
+            });
+    }
+}
@@ -0,0 +1,2369 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Range;
+
+use exmex::{lazy_static::lazy_static, regex, MakeOperators};
+
+use super::cache::{CompiledExpression, ExpressionCache};
+use super::complex;
+use super::geometry::{Command, Object, Parameter, Shape};
+use super::integral;
+use super::ops::{self, Ops};
+
+lazy_static! {
+    static ref POINT_REGEX: regex::Regex =
+        regex::Regex::new(r"^\s*([a-zA-Zα-ωΑ-Ω_][a-zA-Zα-ωΑ-Ω_0-9]*)\s*=\s*\((.*)\)\s*$").unwrap();
+    static ref VARIABLE_REGEX: regex::Regex =
+        regex::Regex::new(r"^\s*([a-zA-Zα-ωΑ-Ω_][a-zA-Zα-ωΑ-Ω_0-9]*)\s*=(.+)$").unwrap();
+    static ref IDENT_REGEX: regex::Regex =
+        regex::Regex::new(r"^[a-zA-Zα-ωΑ-Ω_][a-zA-Zα-ωΑ-Ω_0-9]*").unwrap();
+    static ref NUMBER_REGEX: regex::Regex = regex::Regex::new(r"^\.?[0-9]+(\.[0-9]+)?([eE][+-]?[0-9]+)?").unwrap();
+    static ref RANGE_REGEX: regex::Regex =
+        regex::Regex::new(r"^\s*(.+?)\s*<=?\s*(\w+)\s*<=?\s*(.+?)\s*$").unwrap();
+    static ref FUNCTION_REGEX: regex::Regex =
+        regex::Regex::new(r"^\s*([a-zA-Zα-ωΑ-Ω_][a-zA-Zα-ωΑ-Ω_0-9]*)\s*\(([^()=]*)\)\s*=(.+)$")
+            .unwrap();
+    static ref DIFFERENTIAL_REGEX: regex::Regex =
+        regex::Regex::new(r"^\s*(?:d([xy])\s*/\s*d([xt])|([xy])\s*')\s*=(.+)$").unwrap();
+    static ref POLAR_REGEX: regex::Regex = regex::Regex::new(r"^\s*r\s*=(.+)$").unwrap();
+    static ref CONSTRUCTION_REGEX: regex::Regex = regex::Regex::new(
+        r"^\s*(?:([a-zA-Zα-ωΑ-Ω_][a-zA-Zα-ωΑ-Ω_0-9]*)\s*=\s*)?([a-zA-Z]+)\s*\((.*)\)\s*$"
+    )
+    .unwrap();
+    static ref COMMAND_REGEX: regex::Regex =
+        regex::Regex::new(r"^\s*([a-zA-Z]+)\s*\((.*)\)\s*$").unwrap();
+    static ref BUILTINS: Vec<&'static str> = Ops::make().iter().map(|op| op.repr()).collect();
+    /// Names an input can call besides user-defined functions and commands.
+    pub static ref FUNCTIONS: Vec<&'static str> = Ops::make()
+        .iter()
+        .filter(|op| op.has_unary() && op.repr().chars().all(char::is_alphanumeric))
+        .map(|op| op.repr())
+        .chain(VECTOR_FUNCTIONS.iter().copied())
+        .chain(["integral", "tangent"])
+        .collect();
+    pub static ref CONSTANTS: Vec<&'static str> = Ops::make()
+        .iter()
+        .filter(|op| op.constant().is_some())
+        .map(|op| op.repr())
+        .chain(ALIASES.iter().map(|(alias, _)| *alias).filter(|alias| *alias != "theta"))
+        .collect();
+}
+
+/// Functions of vectors, expanded into their components.
+const VECTOR_FUNCTIONS: &[&str] = &["dot", "norm", "proj"];
+
+/// Spellings accepted in inputs for names exmex knows under another symbol.
+const ALIASES: &[(&str, &str)] = &[("pi", "π"), ("tau", "τ"), ("theta", "θ")];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Number,
+    Ident,
+    /// A coordinate access such as `A.x`.
+    Member,
+    Symbol,
+}
+
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+pub fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next() {
+        if c.is_whitespace() {
+            i += c.len_utf8();
+            continue;
+        }
+
+        let rest = &text[i..];
+        let (kind, len) = if let Some(number) = NUMBER_REGEX.find(rest) {
+            (TokenKind::Number, number.end())
+        } else if let Some(ident) = IDENT_REGEX.find(rest) {
+            let after = &rest[ident.end()..];
+            let is_member = (after.starts_with(".x") || after.starts_with(".y"))
+                && !after[2..].starts_with(|c: char| c.is_alphanumeric() || c == '_');
+            if is_member {
+                (TokenKind::Member, ident.end() + 2)
+            } else {
+                (TokenKind::Ident, ident.end())
+            }
+        } else {
+            (TokenKind::Symbol, c.len_utf8())
+        };
+
+        tokens.push(Token {
+            kind,
+            span: i..i + len,
+        });
+        i += len;
+    }
+    tokens
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SymbolError {
+    Syntax(String),
+    Undefined(Vec<String>),
+    Duplicate(String),
+    WrongKind {
+        name: String,
+        expected: &'static str,
+    },
+    Cycle(Vec<String>),
+    Dependency(String),
+    Arity {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    Evaluation(String),
+}
+
+impl fmt::Display for SymbolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolError::Syntax(msg) => write!(f, "{}", msg),
+            SymbolError::Undefined(names) => write!(f, "undefined: {}", names.join(", ")),
+            SymbolError::Duplicate(name) => write!(f, "{} is defined more than once", name),
+            SymbolError::WrongKind { name, expected } => {
+                write!(f, "{} is not a {}", name, expected)
+            }
+            SymbolError::Cycle(names) => write!(f, "circular definition: {}", names.join(" -> ")),
+            SymbolError::Dependency(name) => write!(f, "depends on invalid {}", name),
+            SymbolError::Arity {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} takes {} argument(s) but {} were given",
+                name, expected, found
+            ),
+            SymbolError::Evaluation(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl SymbolError {
+    /// Byte offset in `input` of what the error is about: the first use of
+    /// a name it mentions, or for a syntax error an unmatched paren, the
+    /// text exmex could not parse, or an operator missing an operand.
+    pub fn position(&self, input: &str) -> Option<usize> {
+        let names = match self {
+            SymbolError::Syntax(message) => return syntax_error_position(input, message),
+            SymbolError::Undefined(names) | SymbolError::Cycle(names) => names.clone(),
+            SymbolError::Duplicate(name)
+            | SymbolError::WrongKind { name, .. }
+            | SymbolError::Dependency(name)
+            | SymbolError::Arity { name, .. } => vec![name.clone()],
+            SymbolError::Evaluation(_) => return None,
+        };
+        tokenize(input)
+            .into_iter()
+            .find(|token| {
+                matches!(token.kind, TokenKind::Ident | TokenKind::Member)
+                    && names
+                        .iter()
+                        .any(|name| symbol(name) == symbol(&input[token.span.clone()]))
+            })
+            .map(|token| token.span.start)
+    }
+}
+
+fn syntax_error_position(input: &str, message: &str) -> Option<usize> {
+    let mut open = Vec::new();
+    for (i, c) in input.char_indices() {
+        match c {
+            '(' => open.push(i),
+            ')' if open.pop().is_none() => return Some(i),
+            _ => {}
+        }
+    }
+    if let Some(&i) = open.last() {
+        return Some(i);
+    }
+
+    // exmex quotes the rest of its own source from where it got stuck.
+    if let Some(rest) = message.strip_prefix("don't know how to parse ") {
+        let rest = rest.replace(['{', '}'], "");
+        return input
+            .rfind(rest.trim())
+            .or_else(|| input.find(rest.chars().next()?));
+    }
+
+    let tokens = tokenize(input);
+    let text = |k: usize| tokens.get(k).map(|token| &input[token.span.clone()]);
+    let is_operator = |k| matches!(text(k), Some("+" | "-" | "*" | "/" | "^"));
+    (0..tokens.len())
+        .find(|&k| {
+            let needs_left = matches!(text(k), Some("*" | "/" | "^"));
+            let after = k.checked_sub(1);
+            let left_missing =
+                after.is_none_or(|p| is_operator(p) || matches!(text(p), Some("(" | ",")));
+            let right_missing = is_operator(k) && matches!(text(k + 1), None | Some(")" | ","));
+            needs_left && left_missing || right_missing
+        })
+        .map(|k| tokens[k].span.start)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Point(f64, f64),
+    Vector(f64, f64),
+    Shape(Shape),
+}
+
+/// An input expression rewritten for exmex, with every reference to another
+/// symbol wrapped in braces so it is bound by name at evaluation time, and
+/// the free variables it is evaluated at. Calls to user-defined functions,
+/// including derivatives such as `f'(x)`, are left as they are and expanded
+/// once the callee is resolved.
+#[derive(Clone, Debug)]
+pub struct Expression {
+    source: String,
+    references: Vec<String>,
+    free: Vec<String>,
+}
+
+impl Expression {
+    fn parse(text: &str, free: &[&str]) -> Result<Self, SymbolError> {
+        let (expression, has_calls) = Self::rewrite(text, free, false)?;
+        if !has_calls {
+            ops::parse(&expression.source).map_err(|e| {
+                // Some exmex messages go on to dump the operators involved.
+                let message = e.to_string();
+                let message = message.split(", Operator {").next().unwrap_or_default();
+                SymbolError::Syntax(message.to_string())
+            })?;
+        }
+        Ok(expression)
+    }
+
+    /// Parses the body of a complex function of `z`, which may use `i` and
+    /// the functions only complex numbers have, such as `conj`.
+    fn parse_complex(text: &str) -> Result<Self, SymbolError> {
+        let (expression, has_calls) = Self::rewrite(text, &["z", "i"], true)?;
+        if !has_calls {
+            complex::parse(&expression.source, |_| Some(1.0)).map_err(SymbolError::Syntax)?;
+        }
+        Ok(expression)
+    }
+
+    /// The expression with its references wrapped, and whether it calls
+    /// user-defined functions, which leaves it to be checked once expanded.
+    fn rewrite(text: &str, free: &[&str], complex: bool) -> Result<(Self, bool), SymbolError> {
+        let mut source = String::new();
+        let mut references: Vec<String> = Vec::new();
+        let mut last = 0;
+        let mut has_calls = false;
+        let tokens = tokenize(text);
+        for (k, token) in tokens.iter().enumerate() {
+            let word = &text[token.span.clone()];
+            let word = ALIASES
+                .iter()
+                .find(|(alias, _)| *alias == word)
+                .map_or(word, |(_, name)| name);
+            source.push_str(&text[last..token.span.start]);
+            last = token.span.end;
+
+            // `2t` and `2(x+1)` are products.
+            let after_number = k
+                .checked_sub(1)
+                .map(|p| &tokens[p])
+                .is_some_and(|p| p.kind == TokenKind::Number && p.span.end == token.span.start);
+            if after_number && (token.kind != TokenKind::Symbol || word == "(") {
+                source.push('*');
+            }
+
+            let is_symbol = token.kind == TokenKind::Member
+                || token.kind == TokenKind::Ident
+                    && !free.contains(&word)
+                    && !BUILTINS.contains(&word)
+                    && (!complex || complex::Function::from_name(word).is_none());
+            let is_call = is_symbol
+                && token.kind == TokenKind::Ident
+                && tokens
+                    .get(k + 1 + primes(text, &tokens[k + 1..]))
+                    .is_some_and(|next| &text[next.span.clone()] == "(");
+
+            let reference = if is_call {
+                has_calls = true;
+                source.push_str(word);
+                format!("{}()", word)
+            } else if is_symbol {
+                source.push('{');
+                source.push_str(word);
+                source.push('}');
+                word.to_string()
+            } else if token.kind == TokenKind::Number && word.contains(['e', 'E']) {
+                // exmex reads no exponents, so `1e-3` is written out in full.
+                let value: f64 = word
+                    .parse()
+                    .map_err(|_| SymbolError::Syntax(word.to_string()))?;
+                source.push_str(&value.to_string());
+                continue;
+            } else {
+                source.push_str(word);
+                continue;
+            };
+            if !references.contains(&reference) {
+                references.push(reference);
+            }
+        }
+        source.push_str(&text[last..]);
+
+        let expression = Self {
+            source,
+            references,
+            free: free.iter().map(|name| name.to_string()).collect(),
+        };
+        Ok((expression, has_calls))
+    }
+
+    /// The value of an expression that is a plain number such as `2` or `-1.5`.
+    pub fn literal(&self) -> Option<f64> {
+        self.source.trim().parse().ok()
+    }
+}
+
+/// A user-defined function with every call in its body already expanded.
+struct UserFunction {
+    params: Vec<String>,
+    source: String,
+    references: Vec<String>,
+}
+
+/// Number of `'` marking a derivative at the start of `tokens`.
+fn primes(text: &str, tokens: &[Token]) -> usize {
+    tokens
+        .iter()
+        .take_while(|token| &text[token.span.clone()] == "'")
+        .count()
+}
+
+fn symbol(reference: &str) -> &str {
+    reference.split(['.', '(']).next().unwrap_or(reference)
+}
+
+fn lookup(values: &HashMap<String, Value>, reference: &str) -> Option<f64> {
+    match (values.get(symbol(reference))?, reference.split_once('.')) {
+        (Value::Number(value), None) => Some(*value),
+        (Value::Point(x, _), Some((_, "x"))) => Some(*x),
+        (Value::Point(_, y), Some((_, "y"))) => Some(*y),
+        (Value::Vector(x, _), Some((_, "x"))) => Some(*x),
+        (Value::Vector(_, y), Some((_, "y"))) => Some(*y),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Definition {
+    Number {
+        name: String,
+        value: Expression,
+    },
+    Point {
+        name: String,
+        x: Expression,
+        y: Expression,
+    },
+    /// A vector such as `v = <2, 1>` or `w = u + 2v at A`, given by its
+    /// components and drawn from `anchor` or the origin.
+    Vector {
+        name: String,
+        x: Expression,
+        y: Expression,
+        anchor: Option<[Expression; 2]>,
+    },
+    /// A function of `x`, possibly piecewise as in `y = x^2 {x < 0}`, with
+    /// the comparisons its pieces begin and end at as `lhs - rhs`.
+    Function {
+        body: Expression,
+        boundaries: Vec<(Expression, Comparison)>,
+    },
+    /// A named function such as `f(x) = x^2` or `h(a, b) = a*b`.
+    UserFunction {
+        name: String,
+        params: Vec<String>,
+        body: Expression,
+        boundaries: Vec<(Expression, Comparison)>,
+    },
+    /// A function of a complex `z` such as `f(z) = (z^2 - 1)/(z^2 + 1)`, in
+    /// which `i` is the imaginary unit.
+    ComplexFunction {
+        name: String,
+        body: Expression,
+    },
+    /// An equation in `x` and `y`, stored as `lhs - rhs`.
+    Implicit {
+        body: Expression,
+    },
+    Parametric {
+        x: Expression,
+        y: Expression,
+        start: Expression,
+        end: Expression,
+    },
+    Polar {
+        radius: Expression,
+        start: Expression,
+        end: Expression,
+    },
+    /// A region where every condition `lhs - rhs <op> 0` holds.
+    Inequality {
+        conditions: Vec<(Expression, Comparison)>,
+    },
+    /// The signed area under `integrand` between two bounds.
+    Integral {
+        integrand: Expression,
+        lower: Expression,
+        upper: Expression,
+    },
+    /// A glider on `function` at `x = at` with its tangent and normal.
+    Tangent {
+        function: Expression,
+        at: Expression,
+    },
+    /// A differential equation `dy/dx = f(x, y)`, or a system
+    /// `x' = f(x, y), y' = g(x, y)` with `dx` as its first equation.
+    Differential {
+        dx: Option<Expression>,
+        dy: Expression,
+    },
+    /// Arrows of the field `(p(x, y), q(x, y))`.
+    VectorField {
+        p: Expression,
+        q: Expression,
+    },
+    /// A geometric object such as `Segment(A, B)` or `c = Circle(A, 2)`, with
+    /// every point argument flattened into its two coordinates and the names
+    /// of the objects it is built from in `shapes`. A function given as an
+    /// expression, as in `Translate(x^2, (1, 0))`, is kept in `graph`.
+    Construction {
+        name: Option<String>,
+        command: Command,
+        args: Vec<Expression>,
+        shapes: Vec<String>,
+        graph: Option<Expression>,
+    },
+}
+
+impl Definition {
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Definition::Number { name, .. }
+            | Definition::Point { name, .. }
+            | Definition::Vector { name, .. }
+            | Definition::ComplexFunction { name, .. }
+            | Definition::UserFunction { name, .. } => Some(name),
+            Definition::Construction { name, .. } => name.as_deref(),
+            Definition::Function { .. }
+            | Definition::Implicit { .. }
+            | Definition::Parametric { .. }
+            | Definition::Polar { .. }
+            | Definition::Inequality { .. }
+            | Definition::Integral { .. }
+            | Definition::Tangent { .. }
+            | Definition::Differential { .. }
+            | Definition::VectorField { .. } => None,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Definition::Number { .. } => "number",
+            Definition::Point { .. } => "point",
+            Definition::Vector { .. } => "vector",
+            Definition::Function { .. } | Definition::UserFunction { .. } => "function",
+            Definition::ComplexFunction { .. } => "complex function",
+            Definition::Implicit { .. } => "equation",
+            Definition::Parametric { .. } => "curve",
+            Definition::Polar { .. } => "polar curve",
+            Definition::Inequality { .. } => "inequality",
+            Definition::Integral { .. } => "integral",
+            Definition::Tangent { .. } => "tangent",
+            Definition::Differential { .. } => "differential equation",
+            Definition::VectorField { .. } => "vector field",
+            Definition::Construction { command, args, .. } => {
+                command.value_kind(args.len()).unwrap_or(command.kind())
+            }
+        }
+    }
+
+    fn expressions(&self) -> Vec<&Expression> {
+        match self {
+            Definition::Number { value, .. } => vec![value],
+            Definition::Point { x, y, .. } => vec![x, y],
+            Definition::Vector { x, y, anchor, .. } => {
+                [x, y].into_iter().chain(anchor.iter().flatten()).collect()
+            }
+            Definition::Function { body, boundaries }
+            | Definition::UserFunction {
+                body, boundaries, ..
+            } => std::iter::once(body)
+                .chain(boundaries.iter().map(|(e, _)| e))
+                .collect(),
+            Definition::Implicit { body } | Definition::ComplexFunction { body, .. } => vec![body],
+            Definition::Parametric { x, y, start, end } => vec![x, y, start, end],
+            Definition::Polar { radius, start, end } => vec![radius, start, end],
+            Definition::Inequality { conditions } => conditions.iter().map(|(e, _)| e).collect(),
+            Definition::Integral {
+                integrand,
+                lower,
+                upper,
+            } => vec![integrand, lower, upper],
+            Definition::Tangent { function, at } => vec![function, at],
+            Definition::Differential { dx, dy } => std::iter::once(dy).chain(dx).collect(),
+            Definition::VectorField { p, q } => vec![p, q],
+            Definition::Construction { args, graph, .. } => args.iter().chain(graph).collect(),
+        }
+    }
+
+    fn references(&self) -> impl Iterator<Item = &String> {
+        self.expressions()
+            .into_iter()
+            .flat_map(|e| e.references.iter())
+    }
+}
+
+type Parser = fn(&str) -> Option<Result<Definition, SymbolError>>;
+
+/// The parsers a line is tried with in order, each with the kind of line it
+/// takes the ones it accepts for. A line none of them accepts is a function
+/// of `x`.
+const PARSERS: &[(&str, Parser)] = &[
+    ("function", parse_function),
+    ("differential equation", parse_differential),
+    ("vector field", parse_vector_field),
+    ("integral", parse_integral),
+    ("tangent", parse_tangent),
+    ("construction", parse_construction),
+    ("point", parse_point),
+    ("vector", |input| parse_vector(input, &HashSet::new())),
+    ("curve", parse_parametric),
+    ("polar curve", parse_polar),
+    ("function", parse_explicit),
+    ("variable", parse_variable),
+    ("inequality", parse_inequality),
+    ("equation", parse_equation),
+];
+
+pub fn parse_definition(input: &str) -> Result<Definition, SymbolError> {
+    parse_line(input).1
+}
+
+/// Parses a line along with the kind of line it was taken for, which is
+/// known even if it fails to parse.
+fn parse_line(input: &str) -> (&'static str, Result<Definition, SymbolError>) {
+    for (kind, parser) in PARSERS {
+        if let Some(definition) = parser(input) {
+            return (kind, definition);
+        }
+    }
+    let function = parse_body(input, &["x"])
+        .map(|(body, boundaries)| Definition::Function { body, boundaries });
+    ("function", function)
+}
+
+/// Parses the body of a function, which may be piecewise.
+fn parse_body(
+    text: &str,
+    free: &[&str],
+) -> Result<(Expression, Vec<(Expression, Comparison)>), SymbolError> {
+    let mut boundaries = Vec::new();
+    let Some(body) = parse_pieces(text, &mut boundaries)? else {
+        return Ok((Expression::parse(text, free)?, Vec::new()));
+    };
+    let boundaries = boundaries
+        .into_iter()
+        .map(|(difference, comparison)| Ok((Expression::parse(&difference, free)?, comparison)))
+        .collect::<Result<_, SymbolError>>()?;
+    Ok((Expression::parse(&body, free)?, boundaries))
+}
+
+/// Rewrites a piecewise body such as `{x < 0: -x, x >= 0: sqrt(x)}`, or one
+/// restricted to a domain such as `x^2 {x < 0}`, into one expression that is
+/// undefined wherever no piece applies. A piece without a condition applies
+/// wherever the ones before it do not. Collects every comparison in the
+/// conditions as `lhs - rhs`.
+fn parse_pieces(
+    text: &str,
+    boundaries: &mut Vec<(String, Comparison)>,
+) -> Result<Option<String>, SymbolError> {
+    let text = text.trim();
+    let Some(inner) = text.strip_suffix('}') else {
+        return Ok(None);
+    };
+    let mut depth = 0;
+    let open = inner.char_indices().rev().find_map(|(i, c)| {
+        match c {
+            '}' => depth += 1,
+            '{' if depth == 0 => return Some(i),
+            '{' => depth -= 1,
+            _ => {}
+        }
+        None
+    });
+    let Some(open) = open else {
+        return Err(SymbolError::Syntax("unmatched }".to_string()));
+    };
+    let (prefix, inner) = (inner[..open].trim(), &inner[open + 1..]);
+
+    let pieces: Vec<(Option<&str>, &str)> = if prefix.is_empty() {
+        split_top_level(inner)
+            .into_iter()
+            .map(|piece| match piece.split_once(':') {
+                Some((condition, value)) => (Some(condition), value),
+                None => (None, piece),
+            })
+            .collect()
+    } else {
+        vec![(Some(inner), prefix)]
+    };
+
+    let mut body = None;
+    for (condition, value) in pieces.into_iter().rev() {
+        if value.trim().is_empty() {
+            return Err(SymbolError::Syntax("empty piece".to_string()));
+        }
+        let piece = match condition {
+            Some(condition) => format!(
+                "(({}) when ({}))",
+                parse_condition(condition, boundaries)?,
+                value
+            ),
+            None => format!("({})", value),
+        };
+        body = Some(match body {
+            Some(rest) => format!("({} otherwise {})", piece, rest),
+            None => piece,
+        });
+    }
+    Ok(body)
+}
+
+/// Rewrites a condition such as `0 <= x < 1 and x < a` or `x < -1 or x > 1`
+/// into an expression that is 1 where it holds and 0 where not, collecting
+/// its comparisons as `lhs - rhs`.
+fn parse_condition(
+    text: &str,
+    boundaries: &mut Vec<(String, Comparison)>,
+) -> Result<String, SymbolError> {
+    let text = text.replace("&&", " and ").replace("||", " or ");
+    let mut alternatives = Vec::new();
+    for alternative in split_words(&text, "or") {
+        let mut all = Vec::new();
+        for part in split_words(alternative, "and") {
+            let (operands, comparisons) = split_comparisons(part);
+            if comparisons.is_empty() {
+                return Err(SymbolError::Syntax(format!(
+                    "{} is not a comparison",
+                    part.trim()
+                )));
+            }
+            for (k, comparison) in comparisons.into_iter().enumerate() {
+                let (lhs, rhs) = (operands[k], operands[k + 1]);
+                all.push(format!("(({}) {} ({}))", lhs, comparison.op(), rhs));
+                boundaries.push((format!("({}) - ({})", lhs, rhs), comparison));
+            }
+        }
+        alternatives.push(format!("({})", all.join("*")));
+    }
+    Ok(alternatives.join(" max "))
+}
+
+fn parse_function(input: &str) -> Option<Result<Definition, SymbolError>> {
+    let caps = FUNCTION_REGEX.captures(input)?;
+    if BUILTINS.contains(&&caps[1]) {
+        return None;
+    }
+    let params: Vec<&str> = caps[2].split(',').map(str::trim).collect();
+    let is_ident = |param: &&str| {
+        IDENT_REGEX
+            .find(param)
+            .is_some_and(|m| m.len() == param.len())
+    };
+    if !params.iter().all(is_ident) {
+        return None;
+    }
+    // A function of `z` is real unless it uses `i` or the parts of a complex
+    // number. A real one is still colored as complex in complex mode.
+    if params == ["z"] && is_complex(&caps[3]) {
+        return Some(
+            Expression::parse_complex(&caps[3]).map(|body| Definition::ComplexFunction {
+                name: caps[1].to_string(),
+                body,
+            }),
+        );
+    }
+    Some(
+        parse_body(&caps[3], &params).map(|(body, boundaries)| Definition::UserFunction {
+            name: caps[1].to_string(),
+            params: params.iter().map(|param| param.to_string()).collect(),
+            body,
+            boundaries,
+        }),
+    )
+}
+
+/// Whether `body` uses the imaginary unit `i`, or calls `re`, `im`, `conj`
+/// or `arg`, which only complex numbers have.
+fn is_complex(body: &str) -> bool {
+    let tokens = tokenize(body);
+    tokens.iter().enumerate().any(|(k, token)| {
+        let word = &body[token.span.clone()];
+        let is_call = tokens
+            .get(k + 1)
+            .is_some_and(|next| &body[next.span.clone()] == "(");
+        token.kind == TokenKind::Ident
+            && (word == "i" && !is_call || is_call && matches!(word, "re" | "im" | "conj" | "arg"))
+    })
+}
+
+/// Parses `dy/dx = f(x, y)`, also written `y' = f(x, y)`, and systems such
+/// as `x' = f(x, y), y' = g(x, y)` or `dx/dt = f(x, y), dy/dt = g(x, y)`.
+fn parse_differential(input: &str) -> Option<Result<Definition, SymbolError>> {
+    let mut equations = Vec::new();
+    for part in split_top_level(input) {
+        let caps = DIFFERENTIAL_REGEX.captures(part)?;
+        let variable = caps.get(1).or(caps.get(3))?.as_str();
+        let respect = caps.get(2).map_or("'", |m| m.as_str());
+        equations.push((variable, respect, caps.get(4)?.as_str()));
+    }
+    let (dx, dy) = match equations[..] {
+        [("y", "x" | "'", dy)] => (None, dy),
+        [("x", "t" | "'", dx), ("y", "t" | "'", dy)]
+        | [("y", "t" | "'", dy), ("x", "t" | "'", dx)] => (Some(dx), dy),
+        _ => return None,
+    };
+    Some((|| {
+        Ok(Definition::Differential {
+            dx: dx
+                .map(|dx| Expression::parse(dx, &["x", "y"]))
+                .transpose()?,
+            dy: Expression::parse(dy, &["x", "y"])?,
+        })
+    })())
+}
+
+fn parse_vector_field(input: &str) -> Option<Result<Definition, SymbolError>> {
+    let args = parse_command(input, "VectorField")?;
+    Some(match args[..] {
+        [p, q] => (|| {
+            Ok(Definition::VectorField {
+                p: Expression::parse(p, &["x", "y"])?,
+                q: Expression::parse(q, &["x", "y"])?,
+            })
+        })(),
+        _ => Err(SymbolError::Syntax(
+            "VectorField takes the two components of the field".to_string(),
+        )),
+    })
+}
+
+/// Parses a variable whose value is vector arithmetic, such as `v = <2, 1>`,
+/// `w = 2u - v at (1, 1)` or `d = dot(u, v)`, where `vectors` are the names
+/// known to be vectors. Lines that turn out to be numbers, like the last
+/// one, are parsed as numbers.
+fn parse_vector(input: &str, vectors: &HashSet<String>) -> Option<Result<Definition, SymbolError>> {
+    let caps = VARIABLE_REGEX.captures(input)?;
+    let name = &caps[1];
+    if name == "x" || name == "y" {
+        return None;
+    }
+    let (value, anchor) = match split_words(&caps[2], "at")[..] {
+        [value] => (value, None),
+        [value, anchor] => (value, Some(anchor)),
+        _ => return None,
+    };
+    let tokens = tokenize(value);
+    let is_vector = tokens.iter().enumerate().any(|(k, token)| {
+        let word = &value[token.span.clone()];
+        let is_call = tokens
+            .get(k + 1)
+            .is_some_and(|next| &value[next.span.clone()] == "(");
+        word == "<" && opens_vector(value, &tokens, k)
+            || token.kind == TokenKind::Ident
+                && (vectors.contains(word) || is_call && VECTOR_FUNCTIONS.contains(&word))
+    });
+    if !is_vector && anchor.is_none() {
+        return None;
+    }
+
+    Some((|| {
+        let x = component(value, 0, vectors)?;
+        let y = component(value, 1, vectors)?;
+        if x != y {
+            Ok(Definition::Vector {
+                name: name.to_string(),
+                x: Expression::parse(&x, &[])?,
+                y: Expression::parse(&y, &[])?,
+                anchor: anchor.map(parse_point_argument).transpose()?,
+            })
+        } else if anchor.is_none() {
+            Ok(Definition::Number {
+                name: name.to_string(),
+                value: Expression::parse(&x, &[])?,
+            })
+        } else {
+            Err(SymbolError::Syntax(format!(
+                "{} is not a vector",
+                value.trim()
+            )))
+        }
+    })())
+}
+
+/// Whether the `<` token `k` of `text` opens a vector `<a, b>` rather than
+/// being a comparison such as `x < 0` or `0 <= θ`: it starts an operand and
+/// is closed around exactly two components.
+fn opens_vector(text: &str, tokens: &[Token], k: usize) -> bool {
+    let starts_operand = k.checked_sub(1).is_none_or(|p| {
+        matches!(
+            &text[tokens[p].span.clone()],
+            "+" | "-" | "*" | "/" | "(" | ","
+        )
+    });
+    let rest = &text[tokens[k].span.end..];
+    starts_operand
+        && !rest.starts_with('=')
+        && matching_bracket(rest).is_some_and(|close| split_brackets(&rest[..close]).len() == 2)
+}
+
+/// The x (`axis` 0) or y (`axis` 1) component of a vector expression, with
+/// each vector in `vectors` replaced by its coordinate, each `<a, b>` by `a`
+/// or `b`, and `dot`, `norm` and `proj` written out.
+fn component(text: &str, axis: usize, vectors: &HashSet<String>) -> Result<String, SymbolError> {
+    let unclosed = || SymbolError::Syntax(format!("unclosed bracket in {}", text.trim()));
+    let tokens = tokenize(text);
+    let mut source = String::new();
+    let mut last = 0;
+    for (k, token) in tokens.iter().enumerate() {
+        if token.span.start < last {
+            continue;
+        }
+        let word = &text[token.span.clone()];
+        source.push_str(&text[last..token.span.start]);
+        last = token.span.end;
+
+        let open = tokens
+            .get(k + 1)
+            .filter(|next| &text[next.span.clone()] == "(")
+            .map(|next| next.span.end);
+        if word == "<" {
+            let close = last + matching_bracket(&text[last..]).ok_or_else(unclosed)?;
+            let [x, y] = split_brackets(&text[last..close])[..] else {
+                return Err(SymbolError::Syntax(
+                    "a vector has two components".to_string(),
+                ));
+            };
+            source.push_str(&format!("({})", component([x, y][axis], axis, vectors)?));
+            last = close + 1;
+        } else if let Some(open) = open.filter(|_| VECTOR_FUNCTIONS.contains(&word)) {
+            let close = open + matching_bracket(&text[open..]).ok_or_else(unclosed)?;
+            let args = split_brackets(&text[open..close]);
+            let part = |arg, axis| component(arg, axis, vectors);
+            let dot = |a, b| -> Result<String, SymbolError> {
+                Ok(format!(
+                    "(({})*({}) + ({})*({}))",
+                    part(a, 0)?,
+                    part(b, 0)?,
+                    part(a, 1)?,
+                    part(b, 1)?
+                ))
+            };
+            let expanded = match (word, &args[..]) {
+                ("dot", &[a, b]) => dot(a, b)?,
+                ("norm", &[a]) => format!("sqrt{}", dot(a, a)?),
+                ("proj", &[a, b]) => {
+                    format!("({}/{}*({}))", dot(a, b)?, dot(b, b)?, part(b, axis)?)
+                }
+                _ => {
+                    return Err(SymbolError::Syntax(format!(
+                        "wrong number of arguments for {}",
+                        word
+                    )))
+                }
+            };
+            source.push_str(&expanded);
+            last = close + 1;
+        } else if token.kind == TokenKind::Ident && open.is_none() && vectors.contains(word) {
+            source.push_str(&format!("{}.{}", word, ["x", "y"][axis]));
+        } else {
+            source.push_str(word);
+        }
+    }
+    source.push_str(&text[last..]);
+    Ok(source)
+}
+
+/// Position of the paren or angle bracket that closes the one `text` follows.
+fn matching_bracket(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' | '<' => depth += 1,
+            ')' | '>' if depth == 0 => return Some(i),
+            ')' | '>' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits `text` at the commas that are not nested in parens or angle
+/// brackets.
+fn split_brackets(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut last = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' | '<' => depth += 1,
+            ')' | '>' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&text[last..i]);
+                last = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[last..]);
+    parts
+}
+
+/// Arguments of a command such as `integral(f, 0, 1)`, whose name is matched
+/// case-insensitively.
+fn parse_command<'a>(input: &'a str, name: &str) -> Option<Vec<&'a str>> {
+    let caps = COMMAND_REGEX.captures(input)?;
+    if !caps[1].eq_ignore_ascii_case(name) {
+        return None;
+    }
+    Some(split_top_level(caps.get(2)?.as_str()))
+}
+
+/// A function argument of a command, either the name of a function such as
+/// `f`, `f'` or `sin`, or an expression in `x`, as an expression in `x`.
+fn parse_graph(text: &str) -> Result<Expression, SymbolError> {
+    let text = text.trim();
+    let name = text.trim_end_matches('\'');
+    let is_name = IDENT_REGEX
+        .find(name)
+        .is_some_and(|m| m.len() == name.len())
+        && name != "x"
+        && (!BUILTINS.contains(&name) || (is_unary_builtin(name) && name == text))
+        && !ALIASES.iter().any(|(alias, _)| *alias == name);
+    if is_name {
+        Expression::parse(&format!("{}(x)", text), &["x"])
+    } else {
+        Expression::parse(text, &["x"])
+    }
+}
+
+/// Whether `name` is a built-in function of one argument, such as `sin`.
+fn is_unary_builtin(name: &str) -> bool {
+    Ops::make()
+        .iter()
+        .any(|op| op.repr() == name && op.has_unary())
+}
+
+/// Parses `integral(f, a, b)`.
+fn parse_integral(input: &str) -> Option<Result<Definition, SymbolError>> {
+    let [integrand, lower, upper] = parse_command(input, "integral")?[..] else {
+        return None;
+    };
+    Some((|| {
+        Ok(Definition::Integral {
+            integrand: parse_graph(integrand)?,
+            lower: Expression::parse(lower, &[])?,
+            upper: Expression::parse(upper, &[])?,
+        })
+    })())
+}
+
+/// Parses `tangent(f, a)`, the tangent and normal of `f` at `x = a`.
+fn parse_tangent(input: &str) -> Option<Result<Definition, SymbolError>> {
+    let [function, at] = parse_command(input, "tangent")?[..] else {
+        return None;
+    };
+    Some((|| {
+        Ok(Definition::Tangent {
+            function: parse_graph(function)?,
+            at: Expression::parse(at, &[])?,
+        })
+    })())
+}
+
+/// Parses geometric commands such as `Segment(A, B)`, optionally named as in
+/// `c = Circle(A, 2)`.
+fn parse_construction(input: &str) -> Option<Result<Definition, SymbolError>> {
+    let caps = CONSTRUCTION_REGEX.captures(input)?;
+    let command = Command::from_name(&caps[2])?;
+    let args = split_top_level(caps.get(3)?.as_str());
+    Some((|| {
+        let parameters = command.parameters(args.len()).ok_or_else(|| {
+            SymbolError::Syntax(format!("wrong number of arguments for {}", command.kind()))
+        })?;
+        let mut expressions = Vec::new();
+        let mut shapes = Vec::new();
+        let mut graph = None;
+        for (arg, parameter) in args.into_iter().zip(parameters) {
+            match parameter {
+                Parameter::Point => expressions.extend(parse_point_argument(arg)?),
+                Parameter::Number => expressions.push(Expression::parse(arg, &[])?),
+                Parameter::Matrix => expressions.extend(parse_matrix(arg)?),
+                Parameter::Object => {
+                    let arg = arg.trim();
+                    let is_name = IDENT_REGEX.find(arg).is_some_and(|m| m.len() == arg.len());
+                    if is_name && arg != "x" {
+                        shapes.push(arg.to_string());
+                    } else {
+                        graph = Some(parse_graph(arg)?);
+                    }
+                }
+                Parameter::Shape => {
+                    let arg = arg.trim();
+                    let is_name = IDENT_REGEX.find(arg).is_some_and(|m| m.len() == arg.len());
+                    if !is_name {
+                        return Err(SymbolError::Syntax(format!(
+                            "{} is not the name of an object",
+                            arg
+                        )));
+                    }
+                    shapes.push(arg.to_string());
+                }
+            }
+        }
+        Ok(Definition::Construction {
+            name: caps.get(1).map(|name| name.as_str().to_string()),
+            command,
+            args: expressions,
+            shapes,
+            graph,
+        })
+    })())
+}
+
+/// The coordinates of a point argument, either the name of a point or a
+/// pair such as `(1, a)`.
+fn parse_point_argument(text: &str) -> Result<[Expression; 2], SymbolError> {
+    let text = text.trim();
+    if IDENT_REGEX
+        .find(text)
+        .is_some_and(|m| m.len() == text.len())
+    {
+        return Ok([
+            Expression::parse(&format!("{}.x", text), &[])?,
+            Expression::parse(&format!("{}.y", text), &[])?,
+        ]);
+    }
+    match tuple(text).as_deref() {
+        Some(&[x, y]) => Ok([Expression::parse(x, &[])?, Expression::parse(y, &[])?]),
+        _ => Err(SymbolError::Syntax(format!("{} is not a point", text))),
+    }
+}
+
+/// The entries `a, b, e, c, d, f` of a matrix `((a, b), (c, d))` or
+/// `((a, b, e), (c, d, f))`, with `e` and `f` zero if left out.
+fn parse_matrix(text: &str) -> Result<[Expression; 6], SymbolError> {
+    let not_a_matrix =
+        || SymbolError::Syntax(format!("{} is not a 2x2 or 2x3 matrix", text.trim()));
+    let rows = tuple(text).ok_or_else(not_a_matrix)?;
+    let [first, second] = rows[..] else {
+        return Err(not_a_matrix());
+    };
+    let row = |row: &str| -> Result<[Expression; 3], SymbolError> {
+        match tuple(row).ok_or_else(not_a_matrix)?[..] {
+            [a, b] => Ok([
+                Expression::parse(a, &[])?,
+                Expression::parse(b, &[])?,
+                Expression::parse("0", &[])?,
+            ]),
+            [a, b, e] => Ok([
+                Expression::parse(a, &[])?,
+                Expression::parse(b, &[])?,
+                Expression::parse(e, &[])?,
+            ]),
+            _ => Err(not_a_matrix()),
+        }
+    };
+    let ([a, b, e], [c, d, f]) = (row(first)?, row(second)?);
+    Ok([a, b, e, c, d, f])
+}
+
+/// The parts of a parenthesized tuple such as `(1, a)`.
+fn tuple(text: &str) -> Option<Vec<&str>> {
+    text.trim()
+        .strip_prefix('(')
+        .filter(|inner| matching_paren(inner) == Some(inner.len() - 1))
+        .map(|inner| split_top_level(&inner[..inner.len() - 1]))
+}
+
+/// Parses points such as `P1=(1, 2)` or `B=(A.x+1, A.y)`.
+fn parse_point(input: &str) -> Option<Result<Definition, SymbolError>> {
+    let caps = POINT_REGEX.captures(input)?;
+    let coordinates = caps.get(2)?.as_str();
+    // Rejects `a=(1)+(2)`, whose outer parens are not a pair.
+    if matching_paren(coordinates).is_some() {
+        return None;
+    }
+    let [x, y] = split_top_level(coordinates)[..] else {
+        return None;
+    };
+    Some((|| {
+        Ok(Definition::Point {
+            name: caps[1].to_string(),
+            x: Expression::parse(x, &[])?,
+            y: Expression::parse(y, &[])?,
+        })
+    })())
+}
+
+fn parse_parametric(input: &str) -> Option<Result<Definition, SymbolError>> {
+    let inner = input.trim_start().strip_prefix('(')?;
+    let close = matching_paren(inner)?;
+    let [x, y] = split_top_level(&inner[..close])[..] else {
+        return None;
+    };
+    let rest = &inner[close + 1..];
+    let (start, end) = if rest.trim().is_empty() {
+        ("0", "2pi")
+    } else {
+        parse_range(rest.trim_start().strip_prefix(',')?, "t")?
+    };
+    Some((|| {
+        Ok(Definition::Parametric {
+            x: Expression::parse(x, &["t"])?,
+            y: Expression::parse(y, &["t"])?,
+            start: Expression::parse(start, &[])?,
+            end: Expression::parse(end, &[])?,
+        })
+    })())
+}
+
+fn parse_polar(input: &str) -> Option<Result<Definition, SymbolError>> {
+    let caps = POLAR_REGEX.captures(input)?;
+    let (radius, (start, end)) = match split_top_level(caps.get(1)?.as_str())[..] {
+        [radius] => (radius, ("0", "2pi")),
+        [radius, range] => (radius, parse_range(range, "θ")?),
+        _ => return None,
+    };
+    Some((|| {
+        Ok(Definition::Polar {
+            radius: Expression::parse(radius, &["θ"])?,
+            start: Expression::parse(start, &[])?,
+            end: Expression::parse(end, &[])?,
+        })
+    })())
+}
+
+/// Bounds of a range such as `0 <= t <= 2pi` over `parameter`.
+fn parse_range<'a>(text: &'a str, parameter: &str) -> Option<(&'a str, &'a str)> {
+    let caps = RANGE_REGEX.captures(text)?;
+    let name = caps.get(2)?.as_str();
+    if name != parameter && !ALIASES.contains(&(name, parameter)) {
+        return None;
+    }
+    Some((caps.get(1)?.as_str(), caps.get(3)?.as_str()))
+}
+
+/// Replaces every parameter in `source` with its parenthesized argument.
+pub fn substitute(source: &str, params: &[String], args: &[String]) -> String {
+    let mut substituted = String::new();
+    let mut last = 0;
+    for token in tokenize(source) {
+        let word = &source[token.span.clone()];
+        let Some(k) = params.iter().position(|param| param == word) else {
+            continue;
+        };
+        if token.kind != TokenKind::Ident {
+            continue;
+        }
+        substituted.push_str(&source[last..token.span.start]);
+        substituted.push('(');
+        substituted.push_str(&args[k]);
+        substituted.push(')');
+        last = token.span.end;
+    }
+    substituted.push_str(&source[last..]);
+    substituted
+}
+
+/// Index of the `)` closing a paren that was opened just before `text`.
+fn matching_paren(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits `text` at the commas that are not nested in parens.
+fn split_top_level(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut last = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&text[last..i]);
+                last = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[last..]);
+    parts
+}
+
+/// Parses functions written `y = f(x)`.
+fn parse_explicit(input: &str) -> Option<Result<Definition, SymbolError>> {
+    let caps = VARIABLE_REGEX.captures(input)?;
+    if &caps[1] != "y" || mentions(&caps[2], "y") {
+        return None;
+    }
+    Some(
+        parse_body(&caps[2], &["x"])
+            .map(|(body, boundaries)| Definition::Function { body, boundaries }),
+    )
+}
+
+fn parse_variable(input: &str) -> Option<Result<Definition, SymbolError>> {
+    let caps = VARIABLE_REGEX.captures(input)?;
+    let name = &caps[1];
+    if name == "x" || name == "y" {
+        return None;
+    }
+    Some(
+        Expression::parse(&caps[2], &[]).map(|value| Definition::Number {
+            name: name.to_string(),
+            value,
+        }),
+    )
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Comparison {
+    pub fn holds(self, value: f64) -> bool {
+        match self {
+            Comparison::Less => value < 0.0,
+            Comparison::LessEqual => value <= 0.0,
+            Comparison::Greater => value > 0.0,
+            Comparison::GreaterEqual => value >= 0.0,
+        }
+    }
+
+    pub fn is_strict(self) -> bool {
+        matches!(self, Comparison::Less | Comparison::Greater)
+    }
+
+    /// The operator that compares two operands this way.
+    fn op(self) -> &'static str {
+        match self {
+            Comparison::Less => "lt",
+            Comparison::LessEqual => "le",
+            Comparison::Greater => "gt",
+            Comparison::GreaterEqual => "ge",
+        }
+    }
+}
+
+/// Parses regions such as `y < x^2`, chains such as `0 < y < x` and several
+/// of them joined with `and`.
+fn parse_inequality(input: &str) -> Option<Result<Definition, SymbolError>> {
+    // Comparisons in braces are the conditions of a piecewise function.
+    if input.contains('{') {
+        return None;
+    }
+    let mut parts = Vec::new();
+    for part in split_words(input, "and") {
+        let (operands, comparisons) = split_comparisons(part);
+        for (k, comparison) in comparisons.into_iter().enumerate() {
+            parts.push((operands[k], operands[k + 1], comparison));
+        }
+    }
+    if parts.is_empty() || !mentions(input, "x") && !mentions(input, "y") {
+        return None;
+    }
+
+    Some(
+        parts
+            .into_iter()
+            .map(|(lhs, rhs, comparison)| {
+                let body = format!("({}) - ({})", lhs, rhs);
+                Ok((Expression::parse(&body, &["x", "y"])?, comparison))
+            })
+            .collect::<Result<_, _>>()
+            .map(|conditions| Definition::Inequality { conditions }),
+    )
+}
+
+/// Splits `text` at every top-level `<`, `<=`, `>` and `>=`.
+fn split_comparisons(text: &str) -> (Vec<&str>, Vec<Comparison>) {
+    let mut operands = Vec::new();
+    let mut comparisons = Vec::new();
+    let mut depth = 0;
+    let mut last = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '<' | '>' if depth == 0 => {
+                let inclusive = chars.next_if(|&(_, next)| next == '=').is_some();
+                comparisons.push(match (c, inclusive) {
+                    ('<', false) => Comparison::Less,
+                    ('<', true) => Comparison::LessEqual,
+                    ('>', false) => Comparison::Greater,
+                    _ => Comparison::GreaterEqual,
+                });
+                operands.push(&text[last..i]);
+                last = i + if inclusive { 2 } else { 1 };
+            }
+            _ => {}
+        }
+    }
+    operands.push(&text[last..]);
+    (operands, comparisons)
+}
+
+/// Splits `text` around every identifier token equal to `word`.
+fn split_words<'a>(text: &'a str, word: &str) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut last = 0;
+    for token in tokenize(text) {
+        if token.kind == TokenKind::Ident && &text[token.span.clone()] == word {
+            parts.push(&text[last..token.span.start]);
+            last = token.span.end;
+        }
+    }
+    parts.push(&text[last..]);
+    parts
+}
+
+fn parse_equation(input: &str) -> Option<Result<Definition, SymbolError>> {
+    let (lhs, rhs) = split_equation(input)?;
+    if !mentions(lhs, "x") && !mentions(lhs, "y") && !mentions(rhs, "x") && !mentions(rhs, "y") {
+        return None;
+    }
+    let body = format!("({}) - ({})", lhs, rhs);
+    Some(Expression::parse(&body, &["x", "y"]).map(|body| Definition::Implicit { body }))
+}
+
+/// Splits `input` at its only `=` that is not part of a comparison.
+fn split_equation(input: &str) -> Option<(&str, &str)> {
+    let bytes = input.as_bytes();
+    let mut found = None;
+    for (i, &b) in bytes.iter().enumerate() {
+        let before = i.checked_sub(1).map(|j| bytes[j]);
+        let after = bytes.get(i + 1).copied();
+        if b == b'=' && !matches!(before, Some(b'<' | b'>' | b'!' | b'=')) && after != Some(b'=') {
+            if found.is_some() {
+                return None;
+            }
+            found = Some(i);
+        }
+    }
+    found.map(|i| (&input[..i], &input[i + 1..]))
+}
+
+fn mentions(text: &str, name: &str) -> bool {
+    tokenize(text)
+        .iter()
+        .any(|token| token.kind == TokenKind::Ident && &text[token.span.clone()] == name)
+}
+
+struct Entry {
+    source: String,
+    /// What the line was taken for when it was parsed.
+    kind: &'static str,
+    definition: Result<Definition, SymbolError>,
+    status: Result<(), SymbolError>,
+    compiled: Vec<CompiledExpression>,
+    /// The number, point or object the line evaluates to.
+    value: Option<Value>,
+    /// First derivative of a function line.
+    derivative: Option<CompiledExpression>,
+    /// Whether `definition` was parsed again as vector arithmetic once the
+    /// vectors the line refers to were known.
+    promoted: bool,
+    /// A complex function line, with the symbols it refers to evaluated.
+    complex: Option<complex::Expr>,
+    /// Bounds and value of an `integral(f, a, b)` line.
+    integral: Option<(f64, f64, f64)>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mark {
+    Unvisited,
+    Visiting,
+    Done,
+}
+
+/// Typed definitions of the Cartesian inputs, one per line, together with
+/// the values of every named symbol resolved in dependency order.
+#[derive(Default)]
+pub struct SymbolTable {
+    entries: Vec<Entry>,
+    values: HashMap<String, Value>,
+    versions: HashMap<String, u64>,
+    functions: HashMap<String, UserFunction>,
+    cache: ExpressionCache,
+}
+
+impl SymbolTable {
+    /// Re-parses the lines whose text changed and resolves the table again if
+    /// anything did. Returns whether the table changed.
+    pub fn update<'a>(&mut self, inputs: impl Iterator<Item = &'a str>) -> bool {
+        let mut changed = false;
+        let mut count = 0;
+        for (i, input) in inputs.enumerate() {
+            count += 1;
+            if self.entries.get(i).is_some_and(|e| e.source == input) {
+                continue;
+            }
+            let (kind, definition) = parse_line(input);
+            let entry = Entry {
+                source: input.to_string(),
+                kind,
+                definition,
+                status: Ok(()),
+                compiled: Vec::new(),
+                value: None,
+                derivative: None,
+                promoted: false,
+                complex: None,
+                integral: None,
+            };
+            if i < self.entries.len() {
+                self.entries[i] = entry;
+            } else {
+                self.entries.push(entry);
+            }
+            changed = true;
+        }
+        if self.entries.len() > count {
+            self.entries.truncate(count);
+            changed = true;
+        }
+
+        if changed {
+            self.resolve();
+        }
+        changed
+    }
+
+    pub fn get(&self, i: usize) -> Result<&Definition, &SymbolError> {
+        let entry = &self.entries[i];
+        if let Err(e) = &entry.status {
+            return Err(e);
+        }
+        entry.definition.as_ref()
+    }
+
+    /// What line `i` was taken for, such as a point, a variable or a
+    /// function, even if it has an error.
+    pub fn kind(&self, i: usize) -> &'static str {
+        match &self.entries[i].definition {
+            Ok(Definition::Number { .. }) => "variable",
+            Ok(definition) => definition.kind(),
+            Err(_) => self.entries[i].kind,
+        }
+    }
+
+    /// Every name the lines define, with what it names.
+    pub fn names(&self) -> Vec<(&str, &'static str)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| Some((entry.definition.as_ref().ok()?.name()?, self.kind(i))))
+            .collect()
+    }
+
+    pub fn value(&self, name: &str) -> Option<Value> {
+        self.values.get(name).cloned()
+    }
+
+    /// The number, point or object line `i` evaluates to, named or not.
+    pub fn line_value(&self, i: usize) -> Option<&Value> {
+        self.get(i).ok()?;
+        self.entries[i].value.as_ref()
+    }
+
+    /// Whether line `i` is a point whose coordinates are both plain numbers,
+    /// so that it can be moved on the canvas.
+    pub fn is_free_point(&self, i: usize) -> bool {
+        matches!(
+            self.get(i),
+            Ok(Definition::Point { x, y, .. }) if x.literal().is_some() && y.literal().is_some()
+        )
+    }
+
+    /// Name and value of a number defined by a plain literal such as `a=2`.
+    pub fn literal(&self, i: usize) -> Option<(&str, f64)> {
+        match self.get(i).ok()? {
+            Definition::Number { name, value } => Some((name, value.literal()?)),
+            _ => None,
+        }
+    }
+
+    pub fn compiled(&self, i: usize) -> Option<&[CompiledExpression]> {
+        self.get(i).ok()?;
+        Some(&self.entries[i].compiled)
+    }
+
+    /// Evaluates a function or equation line at the given free variables,
+    /// `x` for functions and `x, y` for equations.
+    pub fn evaluate(&self, i: usize, free: &[f64]) -> Option<f64> {
+        match self.get(i).ok()? {
+            Definition::Function { .. } | Definition::Implicit { .. } => {
+                self.entries[i].compiled.first()?.eval(free)
+            }
+            Definition::UserFunction { params, .. } if params.len() == free.len() => {
+                self.entries[i].compiled.first()?.eval(free)
+            }
+            _ => None,
+        }
+    }
+
+    /// The direction `(x', y')` that a differential equation line gives at
+    /// `(x, y)`, where `x'` is 1 for an equation `dy/dx = f(x, y)`, or the
+    /// value there of a vector field.
+    pub fn field(&self, i: usize, (x, y): (f64, f64)) -> Option<(f64, f64)> {
+        let compiled = &self.entries[i].compiled;
+        match self.get(i).ok()? {
+            Definition::Differential { dx, .. } => {
+                let dy = compiled.first()?.eval(&[x, y])?;
+                let dx = match dx {
+                    Some(_) => compiled.get(1)?.eval(&[x, y])?,
+                    None => 1.0,
+                };
+                Some((dx, dy))
+            }
+            Definition::VectorField { .. } => {
+                Some((compiled[0].eval(&[x, y])?, compiled[1].eval(&[x, y])?))
+            }
+            _ => None,
+        }
+    }
+
+    /// The anchor and the components of a vector line.
+    pub fn vector(&self, i: usize) -> Option<((f64, f64), (f64, f64))> {
+        let &Value::Vector(x, y) = self.line_value(i)? else {
+            return None;
+        };
+        let anchor = match &self.entries[i].compiled[2..] {
+            [ax, ay] => (ax.eval(&[])?, ay.eval(&[])?),
+            _ => (0.0, 0.0),
+        };
+        Some((anchor, (x, y)))
+    }
+
+    pub fn complex(&self, i: usize) -> Option<&complex::Expr> {
+        self.get(i).ok()?;
+        self.entries[i].complex.as_ref()
+    }
+
+    /// The comparisons the pieces of a piecewise function begin and end at,
+    /// as `lhs - rhs`.
+    pub fn boundaries(&self, i: usize) -> Vec<(&CompiledExpression, Comparison)> {
+        let (
+            Ok(
+                Definition::Function { boundaries, .. }
+                | Definition::UserFunction { boundaries, .. },
+            ),
+            Some(compiled),
+        ) = (self.get(i), self.compiled(i))
+        else {
+            return Vec::new();
+        };
+        compiled
+            .iter()
+            .skip(1)
+            .zip(boundaries.iter().map(|&(_, comparison)| comparison))
+            .collect()
+    }
+
+    pub fn has_derivative(&self, i: usize) -> bool {
+        matches!(
+            self.get(i),
+            Ok(Definition::Function { .. } | Definition::UserFunction { .. })
+        ) && self.entries[i].derivative.is_some()
+    }
+
+    /// Bounds and value of an `integral(f, a, b)` line.
+    pub fn integral(&self, i: usize) -> Option<(f64, f64, f64)> {
+        self.entries[i].integral
+    }
+
+    /// The glider `(x, y)` and the slope there of a `tangent(f, a)` line.
+    pub fn tangent(&self, i: usize) -> Option<(f64, f64, f64)> {
+        let Definition::Tangent { .. } = self.get(i).ok()? else {
+            return None;
+        };
+        let [function, at] = &self.entries[i].compiled[..] else {
+            return None;
+        };
+        let x = at.eval(&[])?;
+        let slope = self.entries[i].derivative.as_ref()?.eval(&[x])?;
+        Some((x, function.eval(&[x])?, slope))
+    }
+
+    /// The function whose graph the transformation on line `i` maps, found
+    /// through any transformations of transformed graphs in between.
+    pub fn graph_source(&self, i: usize) -> Option<&CompiledExpression> {
+        let Definition::Construction { shapes, graph, .. } = self.get(i).ok()? else {
+            return None;
+        };
+        let first = shapes.first();
+        if graph.is_some() || first.is_some_and(|name| self.functions.contains_key(name)) {
+            return self.entries[i].compiled.last();
+        }
+        let j = (0..self.entries.len()).find(|&j| {
+            self.get(j)
+                .is_ok_and(|definition| definition.name() == first.map(String::as_str))
+        })?;
+        self.graph_source(j)
+    }
+
+    /// The line whose text holds the x coordinate of the glider on line `i`:
+    /// line `i` itself if the coordinate is a number, or the line of the
+    /// slider variable it is bound to.
+    pub fn glider_source(&self, i: usize) -> Option<usize> {
+        let Definition::Tangent { at, .. } = self.get(i).ok()? else {
+            return None;
+        };
+        if at.literal().is_some() {
+            return Some(i);
+        }
+        let [name] = &at.references[..] else {
+            return None;
+        };
+        if at.source.trim() != format!("{{{}}}", name) {
+            return None;
+        }
+        (0..self.entries.len()).find(|&j| self.literal(j).is_some_and(|(n, _)| n == name))
+    }
+
+    /// Evaluates the derivative of a function line at `x`.
+    pub fn evaluate_derivative(&self, i: usize, x: f64) -> Option<f64> {
+        self.get(i).ok()?;
+        self.entries[i].derivative.as_ref()?.eval(&[x])
+    }
+
+    fn resolve(&mut self) {
+        let previous = std::mem::take(&mut self.values);
+        self.functions.clear();
+        self.promote_vectors();
+
+        let mut names: HashMap<String, usize> = HashMap::new();
+        for (i, entry) in self.entries.iter_mut().enumerate() {
+            entry.value = None;
+            entry.complex = None;
+            entry.integral = None;
+            entry.status = match &entry.definition {
+                Err(e) => Err(e.clone()),
+                Ok(definition) => match definition.name() {
+                    Some(name) if names.contains_key(name) => {
+                        Err(SymbolError::Duplicate(name.to_string()))
+                    }
+                    Some(name) => {
+                        names.insert(name.to_string(), i);
+                        Ok(())
+                    }
+                    None => Ok(()),
+                },
+            };
+        }
+
+        let mut graph = vec![Vec::new(); self.entries.len()];
+        for (i, deps) in graph.iter_mut().enumerate() {
+            let Ok(definition) = &self.entries[i].definition else {
+                continue;
+            };
+            let mut undefined = Vec::new();
+            let mut wrong_kind = None;
+            for reference in definition.references() {
+                let Some(&target) = names.get(symbol(reference)) else {
+                    undefined.push(reference.clone());
+                    continue;
+                };
+                let expected = if reference.ends_with("()") {
+                    "function"
+                } else if reference.contains('.') {
+                    "point"
+                } else {
+                    "number"
+                };
+                if let Ok(target_definition) = &self.entries[target].definition {
+                    // What a transformation makes depends on what it is
+                    // applied to, which is only known once it is evaluated.
+                    let transform = matches!(
+                        target_definition,
+                        Definition::Construction { command, .. } if command.is_transform()
+                    );
+                    // Vectors have coordinates just like points, and so does
+                    // an intersection without an index if it is one point.
+                    let coordinate = expected == "point"
+                        && (target_definition.kind() == "vector"
+                            || matches!(
+                                target_definition,
+                                Definition::Construction {
+                                    command: Command::Intersect,
+                                    args,
+                                    ..
+                                } if args.is_empty()
+                            ));
+                    if target_definition.kind() != expected && !transform && !coordinate {
+                        wrong_kind = Some(SymbolError::WrongKind {
+                            name: symbol(reference).to_string(),
+                            expected,
+                        });
+                    }
+                }
+                deps.push(target);
+            }
+            if let Definition::Construction {
+                command,
+                shapes,
+                graph,
+                ..
+            } = definition
+            {
+                for (k, shape) in shapes.iter().enumerate() {
+                    let Some(&target) = names.get(shape) else {
+                        undefined.push(shape.clone());
+                        continue;
+                    };
+                    if let Ok(target_definition) = &self.entries[target].definition {
+                        let is_shape = matches!(
+                            target_definition,
+                            Definition::Construction { command, args, .. }
+                                if command.value_kind(args.len()).is_none()
+                        );
+                        // The first argument of a transformation may also be
+                        // a point or a function.
+                        let is_object = is_shape
+                            || command.is_transform()
+                                && k == 0
+                                && graph.is_none()
+                                && ["point", "function"].contains(&target_definition.kind());
+                        if !is_object {
+                            wrong_kind = Some(SymbolError::WrongKind {
+                                name: shape.clone(),
+                                expected: "shape",
+                            });
+                        }
+                    }
+                    deps.push(target);
+                }
+            }
+
+            let entry = &mut self.entries[i];
+            if entry.status.is_ok() {
+                if !undefined.is_empty() {
+                    entry.status = Err(SymbolError::Undefined(undefined));
+                } else if let Some(e) = wrong_kind {
+                    entry.status = Err(e);
+                }
+            }
+        }
+
+        let mut marks = vec![Mark::Unvisited; self.entries.len()];
+        let mut stack = Vec::new();
+        let mut order = Vec::new();
+        for i in 0..self.entries.len() {
+            self.visit(i, &graph, &mut marks, &mut stack, &mut order);
+        }
+
+        for i in order {
+            if self.entries[i].status.is_err() {
+                continue;
+            }
+            if let Some(&broken) = graph[i]
+                .iter()
+                .find(|&&dep| self.entries[dep].status.is_err())
+            {
+                let name = self.entries[broken]
+                    .definition
+                    .as_ref()
+                    .ok()
+                    .and_then(|d| d.name());
+                self.entries[i].status = Err(SymbolError::Dependency(
+                    name.unwrap_or_default().to_string(),
+                ));
+                continue;
+            }
+
+            if let Some(e) = self.ambiguous_intersection(i) {
+                self.entries[i].status = Err(e);
+                continue;
+            }
+
+            let (compiled, derivative) = match self.compile(i) {
+                Ok(compiled) => compiled,
+                Err(e) => {
+                    self.entries[i].status = Err(e);
+                    continue;
+                }
+            };
+            let value = match &self.entries[i].definition {
+                Ok(Definition::Number { .. }) => {
+                    compiled[0].eval(&[]).map(|v| Some(Value::Number(v)))
+                }
+                Ok(Definition::Point { .. }) => compiled[0]
+                    .eval(&[])
+                    .zip(compiled[1].eval(&[]))
+                    .map(|(x, y)| Some(Value::Point(x, y))),
+                Ok(Definition::Vector { .. }) => compiled[0]
+                    .eval(&[])
+                    .zip(compiled[1].eval(&[]))
+                    .map(|(x, y)| Some(Value::Vector(x, y))),
+                Ok(Definition::Construction {
+                    command,
+                    args,
+                    shapes,
+                    graph,
+                    ..
+                }) => {
+                    // A function given as an expression is the first argument.
+                    let objects = graph
+                        .as_ref()
+                        .map(|_| Some(Object::Graph))
+                        .into_iter()
+                        .chain(shapes.iter().map(|name| match self.values.get(name) {
+                            Some(&Value::Point(x, y)) => Some(Object::Point((x, y))),
+                            Some(Value::Shape(shape)) => Some(Object::Shape(shape)),
+                            Some(Value::Number(_) | Value::Vector(..)) => None,
+                            None => self.functions.contains_key(name).then_some(Object::Graph),
+                        }))
+                        .collect::<Option<Vec<_>>>();
+                    compiled[..args.len()]
+                        .iter()
+                        .map(|arg| arg.eval(&[]))
+                        .collect::<Option<Vec<_>>>()
+                        .zip(objects)
+                        .and_then(|(args, objects)| command.evaluate(&args, &objects))
+                        .map(Some)
+                }
+                _ => Some(None),
+            };
+            if let (Ok(Definition::Integral { .. }), [integrand, lower, upper]) =
+                (&self.entries[i].definition, &compiled[..])
+            {
+                self.entries[i].integral =
+                    lower.eval(&[]).zip(upper.eval(&[])).and_then(|(a, b)| {
+                        let value = integral::integrate(|x| integrand.eval(&[x]), a, b)?;
+                        Some((a, b, value))
+                    });
+            }
+            let name = self.entries[i]
+                .definition
+                .as_ref()
+                .ok()
+                .and_then(|d| d.name())
+                .map(String::from);
+            self.entries[i].compiled = compiled;
+            self.entries[i].derivative = derivative;
+            match value {
+                Some(value) => {
+                    if let (Some(name), Some(value)) = (name, &value) {
+                        if previous.get(&name) != Some(value) {
+                            *self.versions.entry(name.clone()).or_default() += 1;
+                        }
+                        self.values.insert(name, value.clone());
+                    }
+                    self.entries[i].value = value;
+                }
+                None => {
+                    self.entries[i].status =
+                        Err(SymbolError::Evaluation("could not evaluate".to_string()))
+                }
+            }
+        }
+
+        self.cache.sweep();
+    }
+
+    /// Parses the lines that do arithmetic on vectors again once it is known
+    /// which names are vectors, so that `w = u + v` is a vector if `u` is,
+    /// until no more lines turn out to be vectors.
+    fn promote_vectors(&mut self) {
+        for entry in &mut self.entries {
+            if entry.promoted {
+                entry.definition = parse_definition(&entry.source);
+                entry.promoted = false;
+            }
+        }
+
+        let mut vectors = HashSet::new();
+        loop {
+            for entry in &self.entries {
+                if let Ok(Definition::Vector { name, .. }) = &entry.definition {
+                    vectors.insert(name.clone());
+                }
+            }
+            let mut changed = false;
+            for entry in &mut self.entries {
+                // Only lines that were read as variables, or not at all.
+                if !matches!(
+                    entry.definition,
+                    Ok(Definition::Number { .. } | Definition::Vector { .. }) | Err(_)
+                ) {
+                    continue;
+                }
+                let Some(definition) = parse_vector(&entry.source, &vectors) else {
+                    continue;
+                };
+                let is_new_vector = matches!(
+                    &definition,
+                    Ok(Definition::Vector { name, .. }) if !vectors.contains(name)
+                );
+                changed |= is_new_vector;
+                entry.definition = definition;
+                entry.promoted = true;
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// Compiles every expression of line `i`, and the derivative of the line
+    /// if it is a function of one variable.
+    fn compile(
+        &mut self,
+        i: usize,
+    ) -> Result<(Vec<CompiledExpression>, Option<CompiledExpression>), SymbolError> {
+        let Ok(definition) = &self.entries[i].definition else {
+            return Ok((Vec::new(), None));
+        };
+        // Functions that a transformation is applied to by name are compiled
+        // after the other arguments, as graphs in `x`.
+        let graphs = match definition {
+            Definition::Construction { shapes, .. } => shapes
+                .iter()
+                .filter(|name| self.functions.contains_key(*name))
+                .map(|name| Expression::parse(&format!("{}(x)", name), &["x"]))
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => Vec::new(),
+        };
+        let mut expressions = definition.expressions();
+        expressions.extend(&graphs);
+        let expanded = expressions
+            .iter()
+            .map(|expression| expand(&self.functions, &mut self.cache, expression))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Definition::UserFunction { name, params, .. } = definition {
+            let (source, references) = expanded[0].clone();
+            self.functions.insert(
+                name.clone(),
+                UserFunction {
+                    params: params.clone(),
+                    source,
+                    references,
+                },
+            );
+        }
+
+        let complex = match definition {
+            Definition::ComplexFunction { .. } => Some(
+                complex::parse(&expanded[0].0, |name| lookup(&self.values, name))
+                    .map_err(SymbolError::Syntax)?,
+            ),
+            Definition::UserFunction { params, .. } if params == &["z"] => {
+                complex::parse(&expanded[0].0, |name| lookup(&self.values, name)).ok()
+            }
+            _ => None,
+        };
+        // A complex function has no real value to compile.
+        if let Definition::ComplexFunction { .. } = definition {
+            self.entries[i].complex = complex;
+            return Ok((Vec::new(), None));
+        }
+
+        let versions = |references: &[String]| {
+            references
+                .iter()
+                .map(|r| self.versions.get(symbol(r)).copied().unwrap_or_default())
+                .collect::<Vec<_>>()
+        };
+        let compiled = expressions
+            .into_iter()
+            .zip(&expanded)
+            .map(|(expression, (source, references))| {
+                self.cache
+                    .compile(source, &expression.free, versions(references), |name| {
+                        lookup(&self.values, name)
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let variable = match definition {
+            Definition::Function { body, .. } => Some(&body.free),
+            Definition::Tangent { function, .. } => Some(&function.free),
+            Definition::UserFunction { params, .. } if params.len() == 1 => Some(params),
+            _ => None,
+        };
+        let derivative = variable.and_then(|free| {
+            let (source, references) = &expanded[0];
+            let derived = self.cache.derivative(source, &free[0], 1);
+            self.cache
+                .compile(&derived, free, versions(references), |name| {
+                    lookup(&self.values, name)
+                })
+                .ok()
+        });
+        self.entries[i].complex = complex;
+        Ok((compiled, derivative))
+    }
+
+    /// The error for line `i` using the coordinates of an intersection
+    /// without an index that is not a single point.
+    fn ambiguous_intersection(&self, i: usize) -> Option<SymbolError> {
+        let definition = self.entries[i].definition.as_ref().ok()?;
+        let (name, count) = definition.references().find_map(|reference| {
+            let name = reference.split_once('.')?.0;
+            match self.values.get(name)? {
+                Value::Shape(Shape::Points(points)) => Some((name, points.len())),
+                _ => None,
+            }
+        })?;
+        let shapes = self
+            .entries
+            .iter()
+            .find_map(|entry| match &entry.definition {
+                Ok(Definition::Construction {
+                    name: Some(named),
+                    shapes,
+                    ..
+                }) if named == name => Some(shapes.join(", ")),
+                _ => None,
+            })?;
+        Some(SymbolError::Evaluation(format!(
+            "{} is {} points, not one; pick one with Intersect({}, n)",
+            name, count, shapes
+        )))
+    }
+
+    fn visit(
+        &mut self,
+        i: usize,
+        graph: &[Vec<usize>],
+        marks: &mut [Mark],
+        stack: &mut Vec<usize>,
+        order: &mut Vec<usize>,
+    ) {
+        match marks[i] {
+            Mark::Done => return,
+            Mark::Visiting => {
+                let start = stack.iter().position(|&j| j == i).unwrap_or(0);
+                let mut cycle: Vec<String> = stack[start..]
+                    .iter()
+                    .filter_map(|&j| self.entries[j].definition.as_ref().ok()?.name())
+                    .map(String::from)
+                    .collect();
+                cycle.extend(cycle.first().cloned());
+                for &j in &stack[start..] {
+                    self.entries[j].status = Err(SymbolError::Cycle(cycle.clone()));
+                }
+                return;
+            }
+            Mark::Unvisited => {}
+        }
+
+        marks[i] = Mark::Visiting;
+        stack.push(i);
+        for &dep in &graph[i] {
+            self.visit(dep, graph, marks, stack, order);
+        }
+        stack.pop();
+        marks[i] = Mark::Done;
+        order.push(i);
+    }
+}
+
+/// Inlines every call to a user-defined function in `expression`. Returns
+/// the expanded source and every symbol it ends up referencing.
+fn expand(
+    functions: &HashMap<String, UserFunction>,
+    cache: &mut ExpressionCache,
+    expression: &Expression,
+) -> Result<(String, Vec<String>), SymbolError> {
+    let (source, mut references) =
+        expand_calls(functions, cache, &expression.source, &expression.free)?;
+    for reference in &expression.references {
+        if !reference.ends_with("()") && !references.contains(reference) {
+            references.push(reference.clone());
+        }
+    }
+    Ok((source, references))
+}
+
+fn expand_calls(
+    functions: &HashMap<String, UserFunction>,
+    cache: &mut ExpressionCache,
+    source: &str,
+    free: &[String],
+) -> Result<(String, Vec<String>), SymbolError> {
+    let tokens = tokenize(source);
+    let mut expanded = String::new();
+    let mut references: Vec<String> = Vec::new();
+    let mut last = 0;
+    for (k, token) in tokens.iter().enumerate() {
+        let word = &source[token.span.clone()];
+        let order = primes(source, &tokens[k + 1..]);
+        if token.span.start < last
+            || token.kind != TokenKind::Ident
+            || free.iter().any(|name| name == word)
+            || tokens
+                .get(k + 1 + order)
+                .is_none_or(|next| &source[next.span.clone()] != "(")
+        {
+            continue;
+        }
+        let Some(function) = functions.get(word) else {
+            continue;
+        };
+
+        let open = tokens[k + 1 + order].span.end;
+        let close = open
+            + matching_paren(&source[open..])
+                .ok_or_else(|| SymbolError::Syntax(format!("unclosed call to {}", word)))?;
+        let args = split_top_level(&source[open..close]);
+        if args.len() != function.params.len() {
+            return Err(SymbolError::Arity {
+                name: word.to_string(),
+                expected: function.params.len(),
+                found: args.len(),
+            });
+        }
+
+        let body = match &function.params[..] {
+            _ if order == 0 => function.source.clone(),
+            [param] => cache.derivative(&function.source, param, order),
+            _ => {
+                return Err(SymbolError::Syntax(format!(
+                    "only functions of one variable can be differentiated, not {}",
+                    word
+                )))
+            }
+        };
+
+        let mut args_expanded = Vec::new();
+        for arg in args {
+            let (arg, arg_references) = expand_calls(functions, cache, arg, free)?;
+            args_expanded.push(arg);
+            references.extend(arg_references);
+        }
+        references.extend(function.references.iter().cloned());
+
+        expanded.push_str(&source[last..token.span.start]);
+        expanded.push('(');
+        expanded.push_str(&substitute(&body, &function.params, &args_expanded));
+        expanded.push(')');
+        last = close + 1;
+    }
+    expanded.push_str(&source[last..]);
+
+    references.sort();
+    references.dedup();
+    Ok((expanded, references))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(lines: &[&str]) -> SymbolTable {
+        let mut table = SymbolTable::default();
+        table.update(lines.iter().copied());
+        table
+    }
+
+    #[test]
+    fn variables_are_substituted() {
+        let table = table(&["a = 2", "y = tan(x)", "y = a*tan(x)", "b = a + 1"]);
+        assert_eq!(table.value("a"), Some(Value::Number(2.0)));
+        let at = std::f64::consts::FRAC_PI_4;
+        assert!((table.evaluate(1, &[at]).unwrap() - 1.0).abs() < 1e-12);
+        assert!((table.evaluate(2, &[at]).unwrap() - 2.0).abs() < 1e-12);
+        assert_eq!(table.value("b"), Some(Value::Number(3.0)));
+    }
+
+    #[test]
+    fn undefined_names_are_reported() {
+        let table = table(&["y = a*x + b", "c = d"]);
+        assert_eq!(
+            table.get(0).unwrap_err(),
+            &SymbolError::Undefined(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(
+            table.get(1).unwrap_err(),
+            &SymbolError::Undefined(vec!["d".to_string()])
+        );
+    }
+
+    #[test]
+    fn duplicate_definitions_are_reported() {
+        let table = table(&["a = 1", "a = 2", "y = a*x"]);
+        assert!(table.get(0).is_ok());
+        assert_eq!(
+            table.get(1).unwrap_err(),
+            &SymbolError::Duplicate("a".to_string())
+        );
+        assert_eq!(table.evaluate(2, &[3.0]), Some(3.0));
+    }
+
+    #[test]
+    fn cycles_are_reported() {
+        let table = table(&["a = b + 1", "b = a * 2", "c = a", "d = 1"]);
+        assert!(matches!(table.get(0), Err(SymbolError::Cycle(_))));
+        assert!(matches!(table.get(1), Err(SymbolError::Cycle(_))));
+        assert!(table.get(2).is_err());
+        assert_eq!(table.value("d"), Some(Value::Number(1.0)));
+    }
+
+    #[test]
+    fn chained_transforms_keep_the_function() {
+        let table = table(&[
+            "g = Translate(x^2, (1, 0))",
+            "h = Translate(g, (0, 3))",
+            "l = Line((0, 0), (1, 0))",
+            "r = Reflect(g, l)",
+        ]);
+        for i in [0, 1, 3] {
+            let f = table.graph_source(i).unwrap();
+            assert_eq!(f.eval(&[2.0]), Some(4.0));
+        }
+        let Some(Value::Shape(Shape::Graph(map))) = table.value("h") else {
+            panic!("h is not a graph");
+        };
+        assert_eq!(map.apply((2.0, 4.0)), (3.0, 7.0));
+        let Some(Value::Shape(Shape::Graph(map))) = table.value("r") else {
+            panic!("r is not a graph");
+        };
+        assert_eq!(map.apply((2.0, 4.0)), (3.0, -4.0));
+    }
+
+    #[test]
+    fn commands_do_not_shadow_functions() {
+        let table = table(&[
+            "area(x) = x^2",
+            "y = area(x)",
+            "s = Segment((0, 0), (1, 1))",
+        ]);
+        assert_eq!(table.evaluate(1, &[2.0]), Some(4.0));
+        assert!(matches!(table.value("s"), Some(Value::Shape(_))));
+    }
+
+    #[test]
+    fn built_in_functions_are_graphs() {
+        let table = table(&["integral(sin, 0, pi)", "tangent(sin, 1)"]);
+        let (a, b, value) = table.integral(0).unwrap();
+        assert_eq!((a, b), (0.0, std::f64::consts::PI));
+        assert!((value - 2.0).abs() < 1e-9);
+        let (x, y, slope) = table.tangent(1).unwrap();
+        assert_eq!((x, y), (1.0, 1f64.sin()));
+        assert!((slope - 1f64.cos()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn piecewise_bodies_are_not_tangent_graphs() {
+        let is_tangent =
+            |line: &str| matches!(parse_definition(line), Ok(Definition::Tangent { .. }));
+        assert!(is_tangent("tangent(x^2, 1)"));
+        assert!(!is_tangent("tangent({x < 0: -x, x >= 0: sqrt(x)}, 1)"));
+        assert!(!is_tangent("tangent(x^2 {x < 0}, 1)"));
+    }
+
+    #[test]
+    fn scientific_notation_is_one_number() {
+        let table = table(&["a = 1e-3", "y = 2.5e2*x", "y = 2x", "b = 1.5E+2"]);
+        assert_eq!(table.value("a"), Some(Value::Number(0.001)));
+        assert_eq!(table.evaluate(1, &[2.0]), Some(500.0));
+        assert_eq!(table.evaluate(2, &[3.0]), Some(6.0));
+        assert_eq!(table.value("b"), Some(Value::Number(150.0)));
+    }
+
+    #[test]
+    fn comparisons_are_not_vectors() {
+        let table = table(&[
+            "r = sin(4θ), 0 <= θ <= pi",
+            "r = theta, 0 <= theta <= 4pi",
+            "b = 2 {x<0}",
+            "y = 2 {x<0}",
+            "u = <1, 2>",
+            "w = u - <3, -4>",
+        ]);
+        assert_eq!(table.kind(0), "polar curve");
+        assert!(table.get(0).is_ok());
+        assert_eq!(table.kind(1), "polar curve");
+        assert!(table.get(1).is_ok());
+        assert_ne!(table.kind(2), "vector");
+        assert_eq!(table.evaluate(3, &[-1.0]), Some(2.0));
+        assert_eq!(table.evaluate(3, &[1.0]).filter(|y| !y.is_nan()), None);
+        assert_eq!(table.value("u"), Some(Value::Vector(1.0, 2.0)));
+        assert_eq!(table.value("w"), Some(Value::Vector(-2.0, 6.0)));
+    }
+
+    #[test]
+    fn functions_of_z_are_real_unless_marked_complex() {
+        let table = table(&[
+            "g(z) = z + 1",
+            "y = g(x)",
+            "f(z) = conj(z) + i",
+            "re = 2",
+            "y = re*x",
+            "y = im(x)",
+        ]);
+        assert_eq!(table.kind(0), "function");
+        assert_eq!(table.evaluate(1, &[2.0]), Some(3.0));
+        assert!(table.complex(0).is_some());
+        assert_eq!(table.kind(2), "complex function");
+        let w = table
+            .complex(2)
+            .unwrap()
+            .eval(num_complex::Complex64::new(1.0, 2.0));
+        assert_eq!((w.re, w.im), (1.0, -1.0));
+        assert_eq!(table.evaluate(4, &[3.0]), Some(6.0));
+        assert!(table.get(5).is_err());
+    }
+
+    #[test]
+    fn unindexed_intersection_is_a_point_only_if_unique() {
+        let table = table(&[
+            "l1 = Line((0, 0), (1, 1))",
+            "l2 = Line((0, 1), (1, 0))",
+            "P = Intersect(l1, l2)",
+            "M = Midpoint(P, (2, 2))",
+            "c = Circle((0, 0), 1)",
+            "Q = Intersect(c, l1)",
+            "N = Midpoint(Q, (0, 0))",
+        ]);
+        assert_eq!(table.value("P"), Some(Value::Point(0.5, 0.5)));
+        assert_eq!(table.value("M"), Some(Value::Point(1.25, 1.25)));
+        let error = table.get(6).unwrap_err().to_string();
+        assert!(error.contains("Intersect(c, l1, n)"), "{}", error);
+    }
+}